@@ -0,0 +1,69 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use ::age::secrecy::SecretString;
+use ::age::{Encryptor, Recipient};
+use anyhow::{anyhow, Result};
+
+/// Encrypts `plaintext` to a recipient public key (`age1…` or an SSH key).
+/// The result opens with a stock `age -d -i <identity>`.
+pub fn encrypt_to_recipient(plaintext: &[u8], recipient: &str) -> Result<Vec<u8>> {
+    let recipient = parse_recipient(recipient)?;
+    let encryptor = Encryptor::with_recipients(std::iter::once(recipient.as_ref() as &dyn Recipient))
+        .map_err(|e| anyhow!("age encryption failed: {e}"))?;
+    encrypt_with(encryptor, plaintext)
+}
+
+/// Encrypts `plaintext` under a passphrase (scrypt); `age -d` asks for it.
+pub fn encrypt_with_passphrase(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let encryptor = Encryptor::with_user_passphrase(SecretString::from(passphrase.to_string()));
+    encrypt_with(encryptor, plaintext)
+}
+
+fn parse_recipient(recipient: &str) -> Result<Box<dyn Recipient + Send>> {
+    let recipient = recipient.trim();
+    if recipient.starts_with("age1") {
+        return ::age::x25519::Recipient::from_str(recipient)
+            .map(|r| Box::new(r) as Box<dyn Recipient + Send>)
+            .map_err(|e| anyhow!("Invalid age recipient: {e}"));
+    }
+    ::age::ssh::Recipient::from_str(recipient)
+        .map(|r| Box::new(r) as Box<dyn Recipient + Send>)
+        .map_err(|_| anyhow!("Invalid age recipient: expected an age1… or SSH public key"))
+}
+
+fn encrypt_with(encryptor: Encryptor, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut writer = encryptor.wrap_output(&mut out)?;
+    writer.write_all(plaintext)?;
+    writer.finish()?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::age::x25519::Identity;
+    use ::age::Decryptor;
+    use std::io::Read;
+
+    #[test]
+    fn recipient_export_round_trips() {
+        let identity = Identity::generate();
+        let recipient = identity.to_public().to_string();
+        let sealed = encrypt_to_recipient(b"{\"entries\":[]}", &recipient).unwrap();
+        assert!(sealed.starts_with(b"age-encryption.org/v1"));
+
+        let decryptor = Decryptor::new(&sealed[..]).unwrap();
+        let mut reader = decryptor.decrypt(std::iter::once(&identity as &dyn ::age::Identity)).unwrap();
+        let mut opened = Vec::new();
+        reader.read_to_end(&mut opened).unwrap();
+        assert_eq!(opened, b"{\"entries\":[]}");
+    }
+
+    #[test]
+    fn malformed_recipient_is_rejected() {
+        assert!(encrypt_to_recipient(b"x", "age1notakey").is_err());
+        assert!(encrypt_to_recipient(b"x", "ssh-ed25519 AAAA").is_err());
+    }
+}
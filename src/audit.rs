@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use crate::models::{Entry, EntryType};
+use crate::ui::classify_password_strength;
+
+/// Logins below this count are classified on the calling thread even with
+/// the `parallel-audit` feature; spawning costs more than it saves.
+#[cfg(feature = "parallel-audit")]
+const PARALLEL_MIN_LOGINS: usize = 2_048;
+
+/// Findings over a vault's logins, as indexes into the audited slice.
+#[derive(Debug, Default, PartialEq)]
+pub struct AuditReport {
+    pub logins: usize,
+    pub no_totp: Vec<usize>,
+    /// Groups of two or more logins sharing one password, each in vault
+    /// order; groups are ordered by their first member.
+    pub reused: Vec<Vec<usize>>,
+    /// Logins the strength meter rates "Weak".
+    pub weak: Vec<usize>,
+}
+
+pub fn lacks_totp(entry: &Entry) -> bool {
+    entry.entry_type == EntryType::Login && entry.totp_secret.as_ref().is_none_or(|s| s.expose().trim().is_empty())
+}
+
+/// One pass over `entries`: reuse is grouped through a map keyed by the
+/// password itself (borrowed, so no copies of secrets are made), instead of
+/// comparing every pair. Entries with an empty password are skipped for
+/// reuse and strength.
+pub fn audit_entries(entries: &[Entry]) -> AuditReport {
+    let mut report = AuditReport::default();
+    let mut by_password: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut rated = Vec::new();
+    for (idx, entry) in entries.iter().enumerate() {
+        if entry.entry_type != EntryType::Login {
+            continue;
+        }
+        report.logins += 1;
+        if lacks_totp(entry) {
+            report.no_totp.push(idx);
+        }
+        let password = entry.password.expose();
+        if !password.is_empty() {
+            by_password.entry(password).or_default().push(idx);
+            rated.push(idx);
+        }
+    }
+    report.reused = by_password.into_values().filter(|group| group.len() > 1).collect();
+    report.reused.sort_unstable_by_key(|group| group[0]);
+    report.weak = weak_logins(entries, &rated);
+    report
+}
+
+fn is_weak(entry: &Entry) -> bool {
+    classify_password_strength(entry.password.expose()).level == 1
+}
+
+#[cfg(not(feature = "parallel-audit"))]
+fn weak_logins(entries: &[Entry], rated: &[usize]) -> Vec<usize> {
+    rated.iter().copied().filter(|&idx| is_weak(&entries[idx])).collect()
+}
+
+/// Splits the strength check into one chunk per core; results stay in vault
+/// order.
+#[cfg(feature = "parallel-audit")]
+fn weak_logins(entries: &[Entry], rated: &[usize]) -> Vec<usize> {
+    let threads = std::thread::available_parallelism().map_or(1, usize::from);
+    if threads == 1 || rated.len() < PARALLEL_MIN_LOGINS {
+        return rated.iter().copied().filter(|&idx| is_weak(&entries[idx])).collect();
+    }
+    let chunk = rated.len().div_ceil(threads);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = rated
+            .chunks(chunk)
+            .map(|part| {
+                scope.spawn(move || part.iter().copied().filter(|&idx| is_weak(&entries[idx])).collect::<Vec<_>>())
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("audit worker panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::new_uuid;
+
+    fn login(name: &str, password: &str, totp: Option<&str>) -> Entry {
+        Entry {
+            id: new_uuid(),
+            name: name.to_string(),
+            email: format!("{name}@example.com"),
+            password: password.into(),
+            username: None,
+            notes: None,
+            totp_secret: totp.map(Into::into),
+            updated_at: None,
+            fields: Vec::new(),
+            attachments: Vec::new(),
+            entry_type: EntryType::Login,
+            ssh_key: None,
+            card: None,
+            sensitive: false,
+        }
+    }
+
+    #[test]
+    fn groups_reuse_and_flags_weak_and_missing_totp() {
+        let mut note = login("note", "", None);
+        note.entry_type = EntryType::SecureNote;
+        let entries = vec![
+            login("a", "Shared-Passw0rd!", Some("JBSWY3DPEHPK3PXP")),
+            login("b", "abc", None),
+            note,
+            login("c", "Shared-Passw0rd!", None),
+            login("d", "", None),
+            login("e", "abc", Some("JBSWY3DPEHPK3PXP")),
+        ];
+        let report = audit_entries(&entries);
+        assert_eq!(report.logins, 5);
+        assert_eq!(report.no_totp, vec![1, 3, 4]);
+        assert_eq!(report.reused, vec![vec![0, 3], vec![1, 5]]);
+        assert_eq!(report.weak, vec![1, 5]);
+    }
+}
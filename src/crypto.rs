@@ -1,3 +1,5 @@
+use std::time::{Duration, Instant};
+
 use anyhow::{anyhow, Result};
 use argon2::{Algorithm, Argon2, Params, Version};
 use base64::Engine;
@@ -5,14 +7,39 @@ use chacha20poly1305::aead::{Aead, KeyInit};
 use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
 use rand::rngs::OsRng;
 use rand::RngCore;
+use serde::{Deserialize, Serialize};
 
 use crate::models::EncryptedVault;
 
+/// The AEAD tag didn't verify: the key was wrong or the data was altered.
+#[derive(Debug)]
+pub struct DecryptionFailed;
+
+impl std::fmt::Display for DecryptionFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Decryption failed. Wrong password?")
+    }
+}
+
+impl std::error::Error for DecryptionFailed {}
+
+/// Argon2 variant, written lowercase in config and vault files. Any other
+/// value fails deserialization, so a typo is caught at load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KdfAlgorithm {
+    #[default]
+    Argon2id,
+    Argon2i,
+    Argon2d,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct KdfParams {
     pub m_cost: u32,
     pub t_cost: u32,
     pub p_cost: u32,
+    pub algorithm: KdfAlgorithm,
 }
 
 impl Default for KdfParams {
@@ -21,6 +48,7 @@ impl Default for KdfParams {
             m_cost: 19 * 1024,
             t_cost: 2,
             p_cost: 1,
+            algorithm: KdfAlgorithm::Argon2id,
         }
     }
 }
@@ -30,9 +58,14 @@ pub fn derive_key_with_params(
     salt: &[u8],
     params: KdfParams,
 ) -> Result<[u8; 32]> {
+    let algorithm = match params.algorithm {
+        KdfAlgorithm::Argon2id => Algorithm::Argon2id,
+        KdfAlgorithm::Argon2i => Algorithm::Argon2i,
+        KdfAlgorithm::Argon2d => Algorithm::Argon2d,
+    };
     let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
         .map_err(|e| anyhow!("Invalid Argon2 params: {e}"))?;
-    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+    let argon2 = Argon2::new(algorithm, Version::V0x13, params);
     let mut key = [0u8; 32];
     argon2
         .hash_password_into(master_password.as_bytes(), salt, &mut key)
@@ -40,6 +73,22 @@ pub fn derive_key_with_params(
     Ok(key)
 }
 
+/// Memory-hard tuning for this machine: doubles Argon2 memory from the default
+/// until one derivation takes at least `target`, capped at 256 MiB.
+pub fn calibrate_kdf(target: Duration) -> Result<KdfParams> {
+    const MAX_M_COST: u32 = 256 * 1024;
+    let mut params = KdfParams::default();
+    let salt = [0u8; 16];
+    loop {
+        let started = Instant::now();
+        derive_key_with_params("calibration", &salt, params)?;
+        if started.elapsed() >= target || params.m_cost >= MAX_M_COST {
+            return Ok(params);
+        }
+        params.m_cost = (params.m_cost * 2).min(MAX_M_COST);
+    }
+}
+
 fn derive_key(master_password: &str, salt: &[u8]) -> Result<[u8; 32]> {
     derive_key_with_params(master_password, salt, KdfParams::default())
 }
@@ -68,7 +117,7 @@ pub fn decrypt_with_key(key: &[u8; 32], enc: &EncryptedVault) -> Result<Vec<u8>>
     let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
     cipher
         .decrypt(Nonce::from_slice(&nonce_bytes), ciphertext.as_ref())
-        .map_err(|_| anyhow!("Decryption failed. Wrong password?"))
+        .map_err(|_| DecryptionFailed.into())
 }
 
 // Legacy password-based encryption (kept for migration)
@@ -102,5 +151,5 @@ pub fn decrypt_with_password(master_password: &str, enc: &EncryptedVault) -> Res
     let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
     cipher
         .decrypt(Nonce::from_slice(&nonce_bytes), ciphertext.as_ref())
-        .map_err(|_| anyhow!("Decryption failed. Wrong password?"))
+        .map_err(|_| DecryptionFailed.into())
 }
@@ -0,0 +1,315 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use zeroize::Zeroize;
+
+use crate::models::{new_uuid, unix_now, Entry, EntryType};
+
+/// Rows between progress callbacks while importing.
+pub const PROGRESS_EVERY_ROWS: usize = 250;
+
+/// Logins read from a CSV export, plus the rows that couldn't be used.
+pub struct CsvImport {
+    pub entries: Vec<(usize, Entry)>,
+    /// Line number (1-based, where the row starts) and reason.
+    pub failed: Vec<(usize, String)>,
+}
+
+/// Column positions resolved from the header row. Names cover the Chrome,
+/// Firefox and Bitwarden export layouts, matched case-insensitively.
+struct Columns {
+    name: Option<usize>,
+    url: Option<usize>,
+    login: Option<usize>,
+    password: usize,
+    notes: Option<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &[String]) -> Result<Self> {
+        let find = |names: &[&str]| {
+            header
+                .iter()
+                .position(|h| names.iter().any(|n| h.trim().eq_ignore_ascii_case(n)))
+        };
+        let password = find(&["password", "login_password"])
+            .ok_or_else(|| anyhow!("CSV header has no password column"))?;
+        let columns = Columns {
+            name: find(&["name", "title"]),
+            url: find(&["url", "login_uri", "uri", "website"]),
+            login: find(&["username", "login_username", "email", "login"]),
+            password,
+            notes: find(&["note", "notes", "extra", "comments"]),
+        };
+        if columns.name.is_none() && columns.url.is_none() {
+            return Err(anyhow!("CSV header has neither a name nor a url column"));
+        }
+        Ok(columns)
+    }
+}
+
+/// Maps every data row to an entry, collecting per-row failures instead of
+/// stopping at the first bad line. `progress` gets (rows done, total rows).
+pub fn entries_from_csv(text: &str, mut progress: impl FnMut(usize, usize)) -> Result<CsvImport> {
+    let mut records = parse_records(text)?;
+    if records.is_empty() {
+        return Err(anyhow!("CSV file is empty"));
+    }
+    let (_, header) = records.remove(0);
+    let columns = Columns::from_header(&header)?;
+    let total = records.len();
+    let mut import = CsvImport {
+        entries: Vec::new(),
+        failed: Vec::new(),
+    };
+    for (done, (line, mut fields)) in records.into_iter().enumerate() {
+        match entry_from_row(&columns, &fields, header.len()) {
+            Ok(entry) => import.entries.push((line, entry)),
+            Err(e) => import.failed.push((line, e.to_string())),
+        }
+        fields.iter_mut().for_each(Zeroize::zeroize);
+        if (done + 1).is_multiple_of(PROGRESS_EVERY_ROWS) {
+            progress(done + 1, total);
+        }
+    }
+    progress(total, total);
+    Ok(import)
+}
+
+fn entry_from_row(columns: &Columns, fields: &[String], width: usize) -> Result<Entry> {
+    if fields.len() != width {
+        return Err(anyhow!("expected {width} fields, found {}", fields.len()));
+    }
+    let get = |idx: Option<usize>| idx.map(|i| fields[i].trim()).filter(|v| !v.is_empty());
+    let name = get(columns.name)
+        .map(str::to_string)
+        .or_else(|| get(columns.url).and_then(host_from_url))
+        .ok_or_else(|| anyhow!("no service name or URL"))?;
+    let login = get(columns.login).ok_or_else(|| anyhow!("no username or email"))?;
+    let password = &fields[columns.password];
+    if password.is_empty() {
+        return Err(anyhow!("empty password"));
+    }
+    Ok(Entry {
+        id: new_uuid(),
+        name,
+        email: login.to_string(),
+        password: password.as_str().into(),
+        username: None,
+        notes: get(columns.notes).map(str::to_string),
+        totp_secret: None,
+        updated_at: Some(unix_now()),
+        fields: Vec::new(),
+        attachments: Vec::new(),
+        entry_type: EntryType::Login,
+        ssh_key: None,
+        card: None,
+        sensitive: false,
+    })
+}
+
+/// Entries from a hand-written template, plus those that failed validation.
+/// Positions are 1-based indexes into the template's list.
+pub struct TemplateImport {
+    pub entries: Vec<(usize, Entry)>,
+    pub failed: Vec<(usize, String)>,
+}
+
+/// One template item. Unknown keys are rejected so a typo such as `pasword`
+/// doesn't silently generate a password instead.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateEntry {
+    name: String,
+    email: String,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+}
+
+/// Parses a JSON array of `{name, email, username?, notes?, password?}`
+/// objects. Items without a password get one from `generate`; a bad item is
+/// reported without stopping the others.
+pub fn entries_from_template(text: &str, mut generate: impl FnMut() -> String) -> Result<TemplateImport> {
+    let items: Vec<serde_json::Value> =
+        serde_json::from_str(text).map_err(|e| anyhow!("Template is not a JSON array: {e}"))?;
+    let mut import = TemplateImport {
+        entries: Vec::new(),
+        failed: Vec::new(),
+    };
+    for (idx, item) in items.into_iter().enumerate() {
+        let position = idx + 1;
+        let mut parsed = match serde_json::from_value::<TemplateEntry>(item) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                import.failed.push((position, e.to_string()));
+                continue;
+            }
+        };
+        match entry_from_template(&mut parsed, &mut generate) {
+            Ok(entry) => import.entries.push((position, entry)),
+            Err(e) => import.failed.push((position, e.to_string())),
+        }
+        if let Some(password) = parsed.password.as_mut() {
+            password.zeroize();
+        }
+    }
+    Ok(import)
+}
+
+fn entry_from_template(item: &mut TemplateEntry, generate: &mut impl FnMut() -> String) -> Result<Entry> {
+    let name = item.name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("empty name"));
+    }
+    let email = item.email.trim();
+    if email.is_empty() {
+        return Err(anyhow!("'{name}' has an empty email"));
+    }
+    let password = match item.password.as_deref() {
+        Some("") => return Err(anyhow!("'{name}' has an empty password; leave it out to generate one")),
+        Some(password) => password.to_string(),
+        None => generate(),
+    };
+    let trimmed = |v: &Option<String>| v.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+    Ok(Entry {
+        id: new_uuid(),
+        name: name.to_string(),
+        email: email.to_string(),
+        password: password.into(),
+        username: trimmed(&item.username),
+        notes: trimmed(&item.notes),
+        totp_secret: None,
+        updated_at: Some(unix_now()),
+        fields: Vec::new(),
+        attachments: Vec::new(),
+        entry_type: EntryType::Login,
+        ssh_key: None,
+        card: None,
+        sensitive: false,
+    })
+}
+
+/// `https://www.example.com:443/login` -> `example.com`.
+fn host_from_url(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit_once('@').map_or(host, |(_, h)| h);
+    let host = host.split(':').next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// RFC 4180 records with the line each one starts on. Quoted fields may hold
+/// commas, doubled quotes and newlines; CRLF and LF line endings both work.
+fn parse_records(text: &str) -> Result<Vec<(usize, Vec<String>)>> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '"' if field.is_empty() => in_quotes = true,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                fields.push(std::mem::take(&mut field));
+                if !(fields.len() == 1 && fields[0].is_empty()) {
+                    records.push((record_line, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                line += 1;
+                record_line = line;
+            }
+            _ => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if in_quotes {
+        field.zeroize();
+        return Err(anyhow!("Unterminated quoted field starting on line {record_line}"));
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((record_line, fields));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(text: &str) -> CsvImport {
+        entries_from_csv(text, |_, _| {}).unwrap()
+    }
+
+    #[test]
+    fn quoted_fields_keep_commas_and_doubled_quotes() {
+        let csv = "name,url,username,password,note\n\
+                   \"Bank, Inc\",,me@example.com,\"pa,ss\"\"word\",\"say \"\"hi\"\"\"\n";
+        let parsed = import(csv);
+        assert!(parsed.failed.is_empty());
+        let (line, entry) = &parsed.entries[0];
+        assert_eq!(*line, 2);
+        assert_eq!(entry.name, "Bank, Inc");
+        assert_eq!(entry.password.expose(), "pa,ss\"word");
+        assert_eq!(entry.notes.as_deref(), Some("say \"hi\""));
+    }
+
+    #[test]
+    fn crlf_line_endings_and_multiline_fields() {
+        let csv = "name,username,password,notes\r\nGit,me,pw1,\"two\r\nlines\"\r\nMail,you,pw2,\r\n";
+        let parsed = import(csv);
+        assert!(parsed.failed.is_empty());
+        let lines: Vec<usize> = parsed.entries.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [2, 4]);
+        assert_eq!(parsed.entries[0].1.notes.as_deref(), Some("two\r\nlines"));
+        assert_eq!(parsed.entries[1].1.password.expose(), "pw2");
+    }
+
+    #[test]
+    fn header_without_required_columns_is_rejected() {
+        assert!(entries_from_csv("name,username\nGit,me\n", |_, _| {}).is_err());
+        assert!(entries_from_csv("username,password\nme,pw\n", |_, _| {}).is_err());
+        assert!(entries_from_csv("", |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn bad_rows_are_reported_by_line() {
+        let csv = "url,login,password\nhttps://www.example.com/login,me,\nhttps://git.io,me\n,me,pw\n";
+        let parsed = import(csv);
+        assert!(parsed.entries.is_empty());
+        assert_eq!(
+            parsed.failed,
+            [
+                (2, "empty password".to_string()),
+                (3, "expected 3 fields, found 2".to_string()),
+                (4, "no service name or URL".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn url_becomes_the_service_name() {
+        let parsed = import("url,username,password\nhttps://user@www.Example.com:8443/path?q,me,pw\n");
+        assert_eq!(parsed.entries[0].1.name, "example.com");
+    }
+}
@@ -1,7 +1,16 @@
+#[cfg(feature = "age")]
+pub mod age;
 pub mod app;
+pub mod audit;
 pub mod crypto;
+pub mod import;
 pub mod models;
+#[cfg(feature = "qr")]
+pub mod qr;
 pub mod storage;
+pub mod totp;
 pub mod ui;
+#[cfg(feature = "yubikey")]
+pub mod yubikey;
 
 pub use app::run;
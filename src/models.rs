@@ -1,5 +1,15 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use serde::{Deserialize, Serialize};
 use rand::RngCore;
+use zeroize::Zeroize;
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
 
 pub fn new_uuid() -> String {
     let mut bytes = [0u8; 16];
@@ -15,17 +25,161 @@ pub fn new_uuid() -> String {
     )
 }
 
+/// Secret text that prints as `[REDACTED]` in `Debug`, is wiped on drop and
+/// is only read through `expose`. Serializes as a plain string.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn expose_mut(&mut self) -> &mut String {
+        &mut self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        SecretString(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        SecretString(value.to_string())
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl Zeroize for SecretString {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Entry {
     #[serde(default = "new_uuid")]
     pub id: String,
     pub name: String,
     pub email: String,
-    pub password: String,
+    pub password: SecretString,
     #[serde(default)]
     pub username: Option<String>,
     #[serde(default)]
     pub notes: Option<String>,
+    #[serde(default)]
+    pub totp_secret: Option<SecretString>,
+    /// Unix seconds of the last password change; `None` for entries saved
+    /// before this was tracked.
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+    #[serde(default)]
+    pub fields: Vec<CustomField>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Entries saved before types existed are logins.
+    #[serde(default)]
+    pub entry_type: EntryType,
+    /// Key material for `EntryType::SshKey`; its passphrase lives in `password`.
+    #[serde(default)]
+    pub ssh_key: Option<SshKey>,
+    #[serde(default)]
+    pub card: Option<Card>,
+    /// Copying the password asks for a confirmation first.
+    #[serde(default)]
+    pub sensitive: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryType {
+    #[default]
+    Login,
+    SshKey,
+    SecureNote,
+    Card,
+}
+
+impl EntryType {
+    pub fn label(self) -> &'static str {
+        match self {
+            EntryType::Login => "Login",
+            EntryType::SshKey => "SSH key",
+            EntryType::SecureNote => "Secure note",
+            EntryType::Card => "Card",
+        }
+    }
+
+    /// What `password` holds for this type, as shown in the UI.
+    pub fn secret_label(self) -> &'static str {
+        match self {
+            EntryType::SshKey => "Passphrase",
+            _ => "Password",
+        }
+    }
+}
+
+/// Payment card details for `EntryType::Card`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Card {
+    pub cardholder: String,
+    /// Digits only; spaces and dashes are stripped on entry.
+    pub number: SecretString,
+    /// As typed, usually `MM/YY`.
+    pub expiry: String,
+    pub cvv: SecretString,
+}
+
+impl Card {
+    /// `•••• 1234`-style display that reveals only the last four digits.
+    pub fn masked_number(&self, mask_char: char) -> String {
+        let number = self.number.expose();
+        let hidden = number.chars().count().saturating_sub(4);
+        let suffix: String = number.chars().skip(hidden).collect();
+        format!("{} {suffix}", std::iter::repeat_n(mask_char, 4).collect::<String>())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SshKey {
+    /// OpenSSH/PEM private key text, exactly as read from the key file.
+    pub private_key: SecretString,
+    #[serde(default)]
+    pub public_key: String,
+}
+
+/// Small file stored inside the encrypted vault (recovery QR, PDF).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attachment {
+    pub name: String,
+    pub mime: String,
+    /// Base64 (standard alphabet) file contents.
+    pub data: SecretString,
+}
+
+/// User-labelled extra value on a credential (security answer, account
+/// number, API key). Secret fields are masked in the detail pane.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CustomField {
+    pub label: String,
+    pub value: SecretString,
+    #[serde(default)]
+    pub secret: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -33,7 +187,7 @@ pub struct Note {
     #[serde(default = "new_uuid")]
     pub id: String,
     pub title: String,
-    pub content: String,
+    pub content: SecretString,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -51,9 +205,30 @@ pub struct Vault {
     pub entries: Vec<Entry>,
     #[serde(default)]
     pub notes: Vec<Note>,
+    /// Unset until the first save after upgrading; read as the defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<crate::storage::VaultSettings>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Meta {
     pub master_hash: String,
 }
+
+/// One `audit.log` record: what happened and to which entry, never a secret.
+#[derive(Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub at: u64,
+    pub kind: String,
+    pub detail: String,
+}
+
+impl AuditEvent {
+    pub fn new(kind: &str, detail: &str) -> Self {
+        Self {
+            at: unix_now(),
+            kind: kind.to_string(),
+            detail: detail.to_string(),
+        }
+    }
+}
@@ -0,0 +1,480 @@
+//! Minimal QR encoder (byte mode, error correction level M, versions 1-10)
+//! for showing `otpauth://` URIs in the terminal. Enough for authenticator
+//! enrolment; not a general-purpose QR library.
+
+use anyhow::{anyhow, Result};
+
+const MAX_VERSION: usize = 10;
+/// Quiet zone in modules around the symbol.
+const QUIET_ZONE: usize = 2;
+
+/// Level-M block layout per version: (ec codewords per block,
+/// blocks in group 1, data codewords per group-1 block, blocks in group 2,
+/// data codewords per group-2 block).
+const EC_BLOCKS_M: [(usize, usize, usize, usize, usize); MAX_VERSION] = [
+    (10, 1, 16, 0, 0),
+    (16, 1, 28, 0, 0),
+    (26, 1, 44, 0, 0),
+    (18, 2, 32, 0, 0),
+    (24, 2, 43, 0, 0),
+    (16, 4, 27, 0, 0),
+    (18, 4, 31, 0, 0),
+    (22, 2, 38, 2, 39),
+    (22, 3, 36, 2, 37),
+    (26, 4, 43, 1, 44),
+];
+
+const ALIGNMENT_POSITIONS: [&[usize]; MAX_VERSION] = [
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+];
+
+/// Format-info bits for level M.
+const EC_LEVEL_M_BITS: u32 = 0b00;
+
+struct Symbol {
+    size: usize,
+    modules: Vec<Vec<bool>>,
+    is_function: Vec<Vec<bool>>,
+}
+
+/// Encodes `text` and renders it with half-block characters, two module rows
+/// per line. Light modules are drawn as filled glyphs so the code reads
+/// correctly on a dark overlay background.
+pub fn render(text: &str) -> Result<Vec<String>> {
+    let symbol = encode(text.as_bytes())?;
+    let full = symbol.size + 2 * QUIET_ZONE;
+    let dark = |x: usize, y: usize| -> bool {
+        if x < QUIET_ZONE || y < QUIET_ZONE {
+            return false;
+        }
+        let (x, y) = (x - QUIET_ZONE, y - QUIET_ZONE);
+        x < symbol.size && y < symbol.size && symbol.modules[y][x]
+    };
+    let mut lines = Vec::with_capacity(full.div_ceil(2));
+    for y in (0..full).step_by(2) {
+        let line = (0..full)
+            .map(|x| match (dark(x, y), y + 1 < full && dark(x, y + 1)) {
+                (false, false) => '█',
+                (false, true) => '▀',
+                (true, false) => '▄',
+                (true, true) => ' ',
+            })
+            .collect();
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+fn encode(data: &[u8]) -> Result<Symbol> {
+    let version = (1..=MAX_VERSION)
+        .find(|&v| data_capacity_bits(v) >= 4 + count_bits(v) + data.len() * 8)
+        .ok_or_else(|| anyhow!("Too long for a QR code ({} bytes)", data.len()))?;
+
+    let codewords = add_error_correction(version, &data_codewords(version, data));
+    let mut symbol = Symbol::new(version);
+    symbol.draw_function_patterns(version);
+    symbol.draw_codewords(&codewords);
+
+    let mut best: Option<(u32, Vec<Vec<bool>>)> = None;
+    for mask in 0..8 {
+        symbol.apply_mask(mask);
+        symbol.draw_format_bits(mask);
+        let penalty = symbol.penalty();
+        if best.as_ref().is_none_or(|(p, _)| penalty < *p) {
+            best = Some((penalty, symbol.modules.clone()));
+        }
+        symbol.apply_mask(mask); // XOR again to undo
+    }
+    symbol.modules = best.expect("eight masks evaluated").1;
+    Ok(symbol)
+}
+
+fn count_bits(version: usize) -> usize {
+    if version < 10 { 8 } else { 16 }
+}
+
+fn data_capacity_bits(version: usize) -> usize {
+    let (_, g1, d1, g2, d2) = EC_BLOCKS_M[version - 1];
+    (g1 * d1 + g2 * d2) * 8
+}
+
+fn data_codewords(version: usize, data: &[u8]) -> Vec<u8> {
+    let capacity = data_capacity_bits(version);
+    let mut bits: Vec<bool> = Vec::with_capacity(capacity);
+    let mut push = |value: usize, len: usize| {
+        for i in (0..len).rev() {
+            bits.push((value >> i) & 1 == 1);
+        }
+    };
+    push(0b0100, 4);
+    push(data.len(), count_bits(version));
+    for &b in data {
+        push(b as usize, 8);
+    }
+    let terminator = (capacity - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    while !bits.len().is_multiple_of(8) {
+        bits.push(false);
+    }
+    let mut out: Vec<u8> = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8))
+        .collect();
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if out.len() * 8 >= capacity {
+            break;
+        }
+        out.push(pad);
+    }
+    out
+}
+
+fn add_error_correction(version: usize, data: &[u8]) -> Vec<u8> {
+    let (ec_len, g1, d1, g2, d2) = EC_BLOCKS_M[version - 1];
+    let divisor = rs_divisor(ec_len);
+    let mut blocks: Vec<&[u8]> = Vec::with_capacity(g1 + g2);
+    let mut offset = 0;
+    for len in std::iter::repeat_n(d1, g1).chain(std::iter::repeat_n(d2, g2)) {
+        blocks.push(&data[offset..offset + len]);
+        offset += len;
+    }
+    let ecc: Vec<Vec<u8>> = blocks.iter().map(|b| rs_remainder(b, &divisor)).collect();
+
+    let mut out = Vec::new();
+    for i in 0..d1.max(d2) {
+        out.extend(blocks.iter().filter_map(|b| b.get(i)));
+    }
+    for i in 0..ec_len {
+        out.extend(ecc.iter().map(|e| e[i]));
+    }
+    out
+}
+
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
+
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &b in data {
+        let factor = b ^ result.remove(0);
+        result.push(0);
+        for (x, &y) in result.iter_mut().zip(divisor) {
+            *x ^= gf_mul(y, factor);
+        }
+    }
+    result
+}
+
+impl Symbol {
+    fn new(version: usize) -> Self {
+        let size = version * 4 + 17;
+        Self {
+            size,
+            modules: vec![vec![false; size]; size],
+            is_function: vec![vec![false; size]; size],
+        }
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y][x] = dark;
+        self.is_function[y][x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        for i in 0..self.size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        let far = self.size - 4;
+        for (cx, cy) in [(3, 3), (far, 3), (3, far)] {
+            self.draw_finder(cx, cy);
+        }
+        let positions = ALIGNMENT_POSITIONS[version - 1];
+        let last = positions.len().saturating_sub(1);
+        for (i, &ax) in positions.iter().enumerate() {
+            for (j, &ay) in positions.iter().enumerate() {
+                let corner = |k: usize| k == 0 || k == last;
+                let overlaps_finder = corner(i) && corner(j) && !(i == last && j == last);
+                if !overlaps_finder {
+                    self.draw_alignment(ax, ay);
+                }
+            }
+        }
+        // Reserve the format areas; real bits are written per mask.
+        self.draw_format_bits(0);
+        self.draw_version(version);
+    }
+
+    fn draw_finder(&mut self, cx: usize, cy: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                if (0..self.size as i32).contains(&x) && (0..self.size as i32).contains(&y) {
+                    let dist = dx.abs().max(dy.abs());
+                    self.set_function(x as usize, y as usize, dist != 2 && dist != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, cx: usize, cy: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                self.set_function((cx as i32 + dx) as usize, (cy as i32 + dy) as usize, dark);
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u32) {
+        let data = (EC_LEVEL_M_BITS << 3) | mask;
+        let mut rem = data;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+        }
+        let bits = ((data << 10) | rem) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 == 1;
+
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        let size = self.size;
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self, version: usize) {
+        if version < 7 {
+            return;
+        }
+        let mut rem = version as u32;
+        for _ in 0..12 {
+            rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+        }
+        let bits = ((version as u32) << 12) | rem;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 == 1;
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let total_bits = data.len() * 8;
+        let mut i = 0;
+        let mut right = self.size as i32 - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..self.size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { self.size - 1 - vert } else { vert };
+                    if !self.is_function[y][x] && i < total_bits {
+                        self.modules[y][x] = (data[i >> 3] >> (7 - (i & 7))) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !self.is_function[y][x] {
+                    self.modules[y][x] = !self.modules[y][x];
+                }
+            }
+        }
+    }
+
+    /// ISO 18004 mask penalty (rules N1-N4).
+    fn penalty(&self) -> u32 {
+        let size = self.size;
+        let get = |x: usize, y: usize| self.modules[y][x];
+        let mut score = 0u32;
+
+        let line_penalty = |line: &[bool]| -> u32 {
+            let mut p = 0;
+            let mut run = 1;
+            for w in line.windows(2) {
+                if w[0] == w[1] {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        p += 3 + (run - 5);
+                    }
+                    run = 1;
+                }
+            }
+            if run >= 5 {
+                p += 3 + (run - 5);
+            }
+            // 1:1:3:1:1 finder-like runs with four light modules on either side.
+            const FINDER: [bool; 11] = [
+                true, false, true, true, true, false, true, false, false, false, false,
+            ];
+            for w in line.windows(11) {
+                if w == FINDER || w.iter().rev().eq(FINDER.iter()) {
+                    p += 40;
+                }
+            }
+            p
+        };
+        for y in 0..size {
+            let row: Vec<bool> = (0..size).map(|x| get(x, y)).collect();
+            score += line_penalty(&row);
+        }
+        for x in 0..size {
+            let col: Vec<bool> = (0..size).map(|y| get(x, y)).collect();
+            score += line_penalty(&col);
+        }
+
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let c = get(x, y);
+                if c == get(x + 1, y) && c == get(x, y + 1) && c == get(x + 1, y + 1) {
+                    score += 3;
+                }
+            }
+        }
+
+        let dark = self.modules.iter().flatten().filter(|&&m| m).count();
+        let total = size * size;
+        let k = (dark * 20).abs_diff(total * 10).div_ceil(total).saturating_sub(1);
+        score + k as u32 * 10
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads the 15 format bits back from around the top-left finder and
+    /// returns (error correction level bits, mask).
+    fn format_info(symbol: &Symbol) -> (u32, u32) {
+        let module = |x: usize, y: usize| u32::from(symbol.modules[y][x]);
+        let mut bits = 0u32;
+        for i in 0..=5 {
+            bits |= module(8, i) << i;
+        }
+        bits |= module(8, 7) << 6;
+        bits |= module(8, 8) << 7;
+        bits |= module(7, 8) << 8;
+        for i in 9..15 {
+            bits |= module(14 - i, 8) << i;
+        }
+        let data = (bits ^ 0x5412) >> 10;
+        (data >> 3, data & 0b111)
+    }
+
+    #[test]
+    fn known_input_has_stable_dimensions() {
+        // 20 bytes overflow version 1 (16 data codewords) and fit version 2.
+        let lines = render("otpauth://totp/a:b?s").unwrap();
+        let full = 25 + 2 * QUIET_ZONE;
+        assert_eq!(lines.len(), full.div_ceil(2));
+        assert!(lines.iter().all(|l| l.chars().count() == full));
+        assert_eq!(lines, render("otpauth://totp/a:b?s").unwrap());
+
+        let uri = crate::totp::otpauth_uri("Acme Co", "alice@example.com", "JBSWY3DPEHPK3PXP");
+        assert_eq!(encode(uri.as_bytes()).unwrap().size, 4 * 6 + 17);
+        assert!(render(&"x".repeat(300)).is_err());
+    }
+
+    #[test]
+    fn codewords_read_back_after_unmasking() {
+        let data = b"otpauth://totp";
+        let mut symbol = encode(data).unwrap();
+        assert_eq!(symbol.size, 21);
+        let (level, mask) = format_info(&symbol);
+        assert_eq!(level, EC_LEVEL_M_BITS);
+        symbol.apply_mask(mask);
+
+        let expected = add_error_correction(1, &data_codewords(1, data));
+        let mut bits = Vec::new();
+        let mut right = symbol.size as i32 - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..symbol.size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let y = if (right + 1) & 2 == 0 { symbol.size - 1 - vert } else { vert };
+                    if !symbol.is_function[y][x] {
+                        bits.push(symbol.modules[y][x]);
+                    }
+                }
+            }
+            right -= 2;
+        }
+        let read: Vec<u8> = bits
+            .chunks(8)
+            .take(expected.len())
+            .map(|byte| byte.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8))
+            .collect();
+        assert_eq!(read, expected);
+        assert_eq!(read[0] >> 4, 0b0100);
+        assert_eq!(usize::from(read[0] & 0x0F) << 4 | usize::from(read[1] >> 4), data.len());
+    }
+}
@@ -1,39 +1,398 @@
 use std::fs;
-use std::io::Write;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Result};
 use base64::Engine;
 use dirs;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
 use rand::rngs::OsRng;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
 
 use crate::crypto::{
-    decrypt_with_key, decrypt_with_password, derive_key_with_params, encrypt_with_key, KdfParams,
+    decrypt_with_key, decrypt_with_password, derive_key_with_params, encrypt_with_key,
+    DecryptionFailed, KdfAlgorithm, KdfParams,
 };
-use crate::models::{EncryptedVault, Meta, Vault};
+use crate::models::{AuditEvent, EncryptedVault, Entry, Meta, Note, Vault};
 
 pub const VAULT_DIR: &str = ".terminal-vault";
 pub const VAULT_FILE: &str = "vault.json";
 pub const LOCK_FILE: &str = "lock.json";
 pub const META_FILE: &str = "meta.json";
 pub const CONFIG_FILE: &str = "config.json";
+/// Shape of `config.json` this build writes. Files without the field are
+/// version 0; `load_config` upgrades older versions and re-saves them.
+pub const CONFIG_VERSION: u32 = 1;
+pub const AUDIT_LOG_FILE: &str = "audit.log";
+/// Trusted revision, encrypted under the DEK, for machines without a keyring.
+pub const TRUSTED_REVISION_FILE: &str = "trusted-revision.json";
 const KEYRING_SERVICE: &str = "terminal-vault";
 const KEYRING_USER: &str = "vault-key";
 const KEYRING_REV_USER: &str = "vault-revision";
-const VAULT_FORMAT_VERSION: u8 = 2;
+pub const VAULT_FORMAT_VERSION: u8 = 2;
 const KDF_SALT_LEN: usize = 16;
+/// Joins the two halves of a split-knowledge credential into the single
+/// passphrase string the rest of the app threads around. Not typeable.
+pub const SPLIT_SEPARATOR: char = '\u{1f}';
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 struct LockState {
     unlock_at: u64,
+    /// When the lock was set; older lock files lack it.
+    #[serde(default)]
+    locked_at: Option<u64>,
+    /// Rejected passphrases from one-shot commands since the last success,
+    /// while no lock is set (`unlock_at` is 0 then).
+    #[serde(default)]
+    failed_attempts: u8,
+}
+
+impl LockState {
+    /// Seconds left at `now`, or `None` once expired. A clock earlier than the
+    /// lock's creation was turned back, so the full duration still applies.
+    fn remaining(&self, now: u64) -> Option<u64> {
+        if let Some(locked_at) = self.locked_at
+            && now < locked_at
+        {
+            return Some(self.unlock_at.saturating_sub(locked_at));
+        }
+        (now < self.unlock_at).then(|| self.unlock_at - now)
+    }
+
+    fn rolled_back(&self, now: u64) -> bool {
+        self.locked_at.is_some_and(|locked_at| now < locked_at)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default)]
+    pub config_version: u32,
     pub vault_dir: String,
+    #[serde(default = "default_mask_char")]
+    pub mask_char: char,
+    #[serde(default)]
+    pub kdf_algorithm: KdfAlgorithm,
+    /// Argon2 memory (KiB) and passes for new wraps; unset means the built-in
+    /// defaults. Written by first-run calibration.
+    #[serde(default)]
+    pub kdf_m_cost: Option<u32>,
+    #[serde(default)]
+    pub kdf_t_cost: Option<u32>,
+    /// Unlock screen without the banner and animation.
+    #[serde(default)]
+    pub minimal_unlock: bool,
+    #[serde(default)]
+    pub keyring_backend: KeyringBackend,
+    /// Directory for `lock.json` when the vault directory isn't writable.
+    #[serde(default)]
+    pub lock_dir: Option<String>,
+    /// Octal modes such as `"0640"` for the files and directory vaulty
+    /// creates, for a trusted group on a shared machine. See
+    /// [`Config::permission_modes`] for the limits.
+    #[serde(default)]
+    pub file_mode: Option<String>,
+    #[serde(default)]
+    pub dir_mode: Option<String>,
+    /// Rules for new master and recovery passphrases.
+    #[serde(default, skip_serializing_if = "PassphrasePolicy::is_default")]
+    pub master_policy: PassphrasePolicy,
+    /// The vault's own settings once it is unlocked. Before that, only keys
+    /// still sitting in `config.json` (to be moved in) or the defaults; it is
+    /// never written back to the file.
+    #[serde(flatten, skip_serializing)]
+    pub settings: VaultSettings,
+}
+
+/// Preferences that say how the vault is used, stored inside the encrypted
+/// blob. `config.json` keeps only what is needed before unlock. This struct is
+/// the one list of them: the keys moved out of `config.json` and the defaults
+/// both come from its serde attributes.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct VaultSettings {
+    #[serde(default)]
+    pub reveal_passwords_by_default: bool,
+    /// Reveal a password generated with Tab in the add form until the cursor
+    /// leaves the password step.
+    #[serde(default)]
+    pub show_generated_password: bool,
+    #[serde(default = "default_password_age_warn_days")]
+    pub password_age_warn_days: u64,
+    #[serde(default = "default_clipboard_clear_secs")]
+    pub clipboard_clear_secs: u64,
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    /// Length of generated passwords; the generator never goes below 12.
+    #[serde(default = "default_gen_length")]
+    pub default_gen_length: usize,
+    /// Auto-clear for `gen --copy`, shorter than `clipboard_clear_secs`
+    /// because a generated password is usually pasted once right away.
+    #[serde(default = "default_gen_clipboard_clear_secs")]
+    pub gen_clipboard_clear_secs: u64,
+    /// Off on machines where the clipboard is unavailable or not allowed: the
+    /// copy keys then reveal secrets on screen for `clipboard_clear_secs`.
+    #[serde(default = "default_clipboard_enabled")]
+    pub clipboard_enabled: bool,
+    /// Symbols the password generator may use, for sites that reject some.
+    #[serde(default = "default_special_chars")]
+    pub special_chars: String,
+    /// Services pane order and selection, restored on the next launch.
+    #[serde(default)]
+    pub service_sort: ServiceSort,
+    #[serde(default)]
+    pub last_service: Option<String>,
+    /// Days between automatic backups taken at launch; unset disables them.
+    #[serde(default)]
+    pub auto_backup_interval_days: Option<u64>,
+    /// Backup target; unset means `backups/` inside the vault directory.
+    #[serde(default)]
+    pub backup_dir: Option<String>,
+    /// Copy through the terminal (OSC 52) instead of the system clipboard, for
+    /// SSH sessions. Also used whenever the system clipboard is unreachable.
+    #[serde(default)]
+    pub use_osc52: bool,
+    /// Width of the notes list as a percentage of the notes screen (20-70).
+    #[serde(default = "default_notes_split_percent")]
+    pub notes_split_percent: u16,
+    /// Shell command run after each unlock that opens the UI.
+    #[serde(default)]
+    pub post_unlock_hook: Option<String>,
+    /// Fields `t` copies in turn, `autotype_delay_secs` apart.
+    #[serde(default = "default_autotype_sequence")]
+    pub autotype_sequence: Vec<AutotypeField>,
+    #[serde(default = "default_autotype_delay_secs")]
+    pub autotype_delay_secs: u64,
+    /// How long a status message stays up; errors and warnings get the
+    /// longer `error_message_secs`.
+    #[serde(default = "default_status_message_secs")]
+    pub status_message_secs: u64,
+    #[serde(default = "default_error_message_secs")]
+    pub error_message_secs: u64,
+    /// Footer bar counting down to the clipboard auto-clear.
+    #[serde(default = "default_clipboard_countdown")]
+    pub clipboard_countdown: bool,
+    /// Passwords Tab offers to pick from in the add and change forms; 1
+    /// fills the field directly.
+    #[serde(default = "default_gen_candidates")]
+    pub gen_candidates: usize,
+    /// Template for each row of the credentials list; see `ui::EntryLabel`.
+    #[serde(default = "default_entry_label")]
+    pub entry_label: String,
+    /// Show each credential's `id` in the detail pane, for scripts that
+    /// reference it with `--show-id`.
+    #[serde(default)]
+    pub show_entry_ids: bool,
+    /// Open the add form with a generated password already in the password
+    /// field.
+    #[serde(default)]
+    pub auto_generate_on_add: bool,
+    /// Compresses the vault JSON before it is encrypted, from the next save.
+    /// Loading follows the vault file's own flag, whatever this says.
+    #[serde(default)]
+    pub vault_compression: VaultCompression,
+}
+
+/// Whether `key` belongs in [`VaultSettings`]. When one shows up in
+/// `config.json` (an older file, or a hand edit) it wins over the vault's
+/// value at the next unlock and is then removed from the file.
+fn is_vault_setting_key(key: &str) -> bool {
+    static KEYS: OnceLock<Vec<String>> = OnceLock::new();
+    KEYS.get_or_init(|| match serde_json::to_value(VaultSettings::default()) {
+        Ok(serde_json::Value::Object(map)) => map.into_iter().map(|(k, _)| k).collect(),
+        _ => Vec::new(),
+    })
+    .iter()
+    .any(|k| k == key)
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceSort {
+    /// Alphabetical.
+    #[default]
+    Name,
+    /// Most recently updated credential first.
+    Recent,
+}
+
+impl ServiceSort {
+    pub fn toggled(self) -> Self {
+        match self {
+            ServiceSort::Name => ServiceSort::Recent,
+            ServiceSort::Recent => ServiceSort::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ServiceSort::Name => "name",
+            ServiceSort::Recent => "recently updated",
+        }
+    }
+}
+
+/// One step of the autotype sequence.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AutotypeField {
+    /// The username, or the email when no username is stored.
+    Username,
+    Email,
+    Password,
+    /// The current TOTP code.
+    Totp,
+}
+
+impl AutotypeField {
+    pub fn label(self) -> &'static str {
+        match self {
+            AutotypeField::Username => "username",
+            AutotypeField::Email => "email",
+            AutotypeField::Password => "password",
+            AutotypeField::Totp => "TOTP code",
+        }
+    }
+}
+
+fn default_autotype_sequence() -> Vec<AutotypeField> {
+    vec![AutotypeField::Username, AutotypeField::Password]
+}
+
+fn default_autotype_delay_secs() -> u64 {
+    5
+}
+
+fn default_status_message_secs() -> u64 {
+    2
+}
+
+fn default_clipboard_countdown() -> bool {
+    true
+}
+
+fn default_clipboard_enabled() -> bool {
+    true
+}
+
+fn default_gen_candidates() -> usize {
+    3
+}
+
+fn default_entry_label() -> String {
+    "{user} ({email})".into()
+}
+
+fn default_error_message_secs() -> u64 {
+    6
+}
+
+fn default_password_age_warn_days() -> u64 {
+    365
+}
+
+fn default_mask_char() -> char {
+    '•'
+}
+
+pub fn default_clipboard_clear_secs() -> u64 {
+    20
+}
+
+pub fn default_idle_timeout_secs() -> u64 {
+    120
+}
+
+fn default_gen_length() -> usize {
+    20
+}
+
+fn default_gen_clipboard_clear_secs() -> u64 {
+    10
+}
+
+fn default_notes_split_percent() -> u16 {
+    35
+}
+
+pub const DEFAULT_SPECIAL_CHARS: &str = "!@#$%^&*()-_=+[]{};:,.?";
+
+fn default_special_chars() -> String {
+    DEFAULT_SPECIAL_CHARS.to_string()
+}
+
+impl Config {
+    /// `file_mode` and `dir_mode` resolved against the strict defaults. Each
+    /// must keep owner access and may add group read (plus search for the
+    /// directory) but nothing more.
+    pub fn permission_modes(&self) -> Result<PermissionModes> {
+        let parse = |key: &str, raw: &Option<String>, strict: u32, max: u32| -> Result<u32> {
+            let Some(raw) = raw else {
+                return Ok(strict);
+            };
+            let digits = raw.trim().trim_start_matches("0o");
+            let mode = u32::from_str_radix(digits, 8)
+                .map_err(|_| anyhow!("{key} must be an octal mode such as \"{strict:04o}\", got \"{raw}\""))?;
+            if mode & strict != strict || mode & !max != 0 {
+                return Err(anyhow!(
+                    "{key} {mode:04o} is not allowed; use {strict:04o} up to {max:04o} (group read at most)"
+                ));
+            }
+            Ok(mode)
+        };
+        Ok(PermissionModes {
+            file: parse("file_mode", &self.file_mode, STRICT_FILE_MODE, MAX_FILE_MODE)?,
+            dir: parse("dir_mode", &self.dir_mode, STRICT_DIR_MODE, MAX_DIR_MODE)?,
+        })
+    }
+}
+
+impl VaultSettings {
+    /// `special_chars` must be ASCII punctuation, and non-empty whenever the
+    /// generator is going to use symbols.
+    pub fn validate_special_chars(&self, symbols: bool) -> Result<()> {
+        if let Some(c) = self.special_chars.chars().find(|c| !c.is_ascii_punctuation()) {
+            return Err(anyhow!("special_chars may only contain ASCII symbols, found {c:?}"));
+        }
+        if symbols && self.special_chars.is_empty() {
+            return Err(anyhow!(
+                "special_chars is empty; list at least one symbol or generate with --no-symbols"
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for VaultSettings {
+    /// Every field at its serde default.
+    fn default() -> Self {
+        serde_json::from_value(serde_json::Value::Object(serde_json::Map::new()))
+            .expect("every vault setting has a default")
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            config_version: CONFIG_VERSION,
+            vault_dir: String::new(),
+            mask_char: default_mask_char(),
+            kdf_algorithm: KdfAlgorithm::default(),
+            kdf_m_cost: None,
+            kdf_t_cost: None,
+            minimal_unlock: false,
+            keyring_backend: KeyringBackend::default(),
+            lock_dir: None,
+            file_mode: None,
+            dir_mode: None,
+            master_policy: PassphrasePolicy::default(),
+            settings: VaultSettings::default(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -41,6 +400,9 @@ struct KdfSpec {
     m_cost: u32,
     t_cost: u32,
     p_cost: u32,
+    /// Vaults written before this field existed used Argon2id.
+    #[serde(default)]
+    algorithm: KdfAlgorithm,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -48,8 +410,60 @@ struct WrappedVaultFile {
     version: u8,
     kdf: KdfSpec,
     kdf_salt: String,
+    /// Present for split-knowledge vaults: the master KEK is the XOR of keys
+    /// derived from two passphrases, the second one using this salt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf_salt_second: Option<String>,
     wrapped_key: EncryptedVault,
     vault: EncryptedVault,
+    /// Plaintext copy of the vault revision so `--verify` can check it against
+    /// the keyring without the passphrase. Advisory only; the encrypted
+    /// revision is what unlock trusts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    revision: Option<u64>,
+    /// Second wrap of the same DEK under a recovery passphrase. While present
+    /// the DEK stays fixed across saves so both wraps remain valid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wrapped_key_recovery: Option<RecoveryWrap>,
+    /// Present once a YubiKey is enrolled: the challenge whose response is
+    /// mixed into the master KEK. The recovery wrap does not need the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    yubikey_challenge: Option<String>,
+    /// How the vault JSON was compressed before encryption; absent in files
+    /// written uncompressed, including every one from before the option.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    compression: Option<VaultCompression>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecoveryWrap {
+    kdf: KdfSpec,
+    kdf_salt: String,
+    wrapped_key: EncryptedVault,
+}
+
+impl KdfSpec {
+    fn from_params(params: KdfParams) -> Self {
+        Self {
+            m_cost: params.m_cost,
+            t_cost: params.t_cost,
+            p_cost: params.p_cost,
+            algorithm: params.algorithm,
+        }
+    }
+
+    fn derive(&self, passphrase: &str, salt_b64: &str) -> Result<[u8; 32]> {
+        let salt = base64::engine::general_purpose::STANDARD
+            .decode(salt_b64)
+            .map_err(|e| anyhow!("Invalid vault salt encoding: {e}"))?;
+        let params = KdfParams {
+            m_cost: self.m_cost,
+            t_cost: self.t_cost,
+            p_cost: self.p_cost,
+            algorithm: self.algorithm,
+        };
+        derive_key_with_params(passphrase, &salt, params)
+    }
 }
 
 pub fn default_base_dir() -> Result<PathBuf> {
@@ -61,30 +475,139 @@ pub fn config_path() -> Result<PathBuf> {
     Ok(default_base_dir()?.join(CONFIG_FILE))
 }
 
+/// Reads `config.json`, upgrading an older version in memory only. Nothing is
+/// written here, so read-only commands leave the file alone; the upgraded
+/// form reaches disk the next time a command saves the config.
 pub fn load_config() -> Result<Option<Config>> {
-    let path = config_path()?;
+    read_config_at(&config_path()?)
+}
+
+fn read_config_at(path: &Path) -> Result<Option<Config>> {
     if !path.exists() {
         return Ok(None);
     }
-    let raw = fs::read_to_string(path)?;
-    let cfg: Config = serde_json::from_str(&raw)?;
+    let mut raw: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| anyhow!("{} is not a JSON object: {e}", path.display()))?;
+    let version = match raw.get("config_version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| anyhow!("config_version in {} must be a number", path.display()))?,
+    };
+    if version > CONFIG_VERSION {
+        return Err(anyhow!(
+            "{} is config version {version}, newer than this build understands ({CONFIG_VERSION}); upgrade vaulty",
+            path.display()
+        ));
+    }
+    if version < CONFIG_VERSION {
+        let base_dir = path.parent().ok_or_else(|| anyhow!("Invalid config path {}", path.display()))?;
+        migrate_config(&mut raw, version, base_dir)?;
+    }
+    let cfg: Config = serde_json::from_value(serde_json::Value::Object(raw))
+        .map_err(|e| anyhow!("Invalid {}: {e}", path.display()))?;
+    cfg.master_policy
+        .validate()
+        .map_err(|e| anyhow!("Invalid {}: {e}", path.display()))?;
     Ok(Some(cfg))
 }
 
+/// Upgrades a raw config from `from` to [`CONFIG_VERSION`], one step at a
+/// time. `base_dir` is the directory the config file lives in, which is the
+/// default vault directory.
+fn migrate_config(raw: &mut serde_json::Map<String, serde_json::Value>, from: u32, base_dir: &Path) -> Result<()> {
+    if from < 1 {
+        // Version 0 files were hand edited or written piecemeal: `null` stood
+        // for "unset" (which now means leaving the key out) and `vault_dir`
+        // could be missing, meaning the default directory.
+        raw.retain(|_, v| !v.is_null());
+        if !raw.contains_key("vault_dir") {
+            let dir = base_dir.to_str().ok_or_else(|| anyhow!("Invalid base dir path"))?;
+            raw.insert("vault_dir".into(), dir.into());
+        }
+    }
+    raw.insert("config_version".into(), CONFIG_VERSION.into());
+    Ok(())
+}
+
 pub fn save_config(base_dir: &Path) -> Result<()> {
-    let cfg = Config {
-        vault_dir: base_dir
-            .to_str()
-            .ok_or_else(|| anyhow!("Invalid base dir path"))?
-            .to_string(),
+    let mut cfg = load_config()?.unwrap_or_default();
+    cfg.vault_dir = base_dir
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid base dir path"))?
+        .to_string();
+    write_config(&cfg)
+}
+
+fn load_raw_config() -> Result<serde_json::Map<String, serde_json::Value>> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(serde_json::Map::new());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Folds any [`VaultSettings`] keys still in `config.json` into `settings`.
+/// Returns whether the vault changed; the caller saves it and only then calls
+/// [`strip_vault_settings_from_config`], so a crash in between leaves the
+/// values in both places rather than in neither.
+pub fn absorb_config_settings(settings: &mut Option<VaultSettings>) -> Result<bool> {
+    let raw = load_raw_config()?;
+    let moved: serde_json::Map<_, _> = raw
+        .into_iter()
+        .filter(|(k, _)| is_vault_setting_key(k))
+        .collect();
+    if moved.is_empty() {
+        return Ok(false);
+    }
+    let current = settings.clone().unwrap_or_default();
+    let serde_json::Value::Object(mut merged) = serde_json::to_value(&current)? else {
+        return Err(anyhow!("Vault settings did not serialize to an object"));
     };
-    if let Some(parent) = config_path()?.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent)?;
-            restrict_dir(parent)?;
+    merged.extend(moved);
+    let updated: VaultSettings = serde_json::from_value(serde_json::Value::Object(merged))
+        .map_err(|e| anyhow!("Invalid setting in config.json: {e}"))?;
+    let changed = settings.as_ref() != Some(&updated);
+    *settings = Some(updated);
+    Ok(changed)
+}
+
+/// Rewrites `config.json` without the keys now held by the vault, if it has
+/// any.
+pub fn strip_vault_settings_from_config() -> Result<()> {
+    let raw = load_raw_config()?;
+    if !raw.keys().any(|k| is_vault_setting_key(k)) {
+        return Ok(());
+    }
+    let cfg: Config = serde_json::from_value(serde_json::Value::Object(raw))?;
+    write_config_file(&cfg, false)
+}
+
+/// Writes the pre-unlock settings. Vault-held keys already in the file are
+/// kept until the next unlock moves them.
+pub fn write_config(cfg: &Config) -> Result<()> {
+    write_config_file(cfg, true)
+}
+
+fn write_config_file(cfg: &Config, keep_unmigrated: bool) -> Result<()> {
+    let mut value = serde_json::to_value(cfg)?;
+    if keep_unmigrated
+        && let serde_json::Value::Object(out) = &mut value
+    {
+        for (key, v) in load_raw_config()? {
+            if is_vault_setting_key(&key) && !v.is_null() {
+                out.insert(key, v);
+            }
         }
     }
-    let data = serde_json::to_string_pretty(&cfg)?;
+    if let Some(parent) = config_path()?.parent()
+        && !parent.exists()
+    {
+        fs::create_dir_all(parent)?;
+        restrict_dir(parent)?;
+    }
+    let data = serde_json::to_string_pretty(&value)?;
     let path = config_path()?;
     atomic_write(path.as_path(), data.as_bytes())?;
     restrict_file(path.as_path())?;
@@ -102,10 +625,82 @@ pub fn vault_path() -> Result<PathBuf> {
     Ok(configured_base_dir()?.join(VAULT_FILE))
 }
 
+/// `lock.json` lives next to the vault unless `lock_dir` moves it to a
+/// writable runtime directory, for vault directories on read-only or network
+/// storage.
 pub fn lock_path() -> Result<PathBuf> {
+    if let Some(raw) = load_config()?.and_then(|cfg| cfg.lock_dir) {
+        // A private subdirectory: the lock dir itself may be shared, like /tmp.
+        return Ok(private_lock_dir(&resolve_lock_dir(&raw)?)?.join(LOCK_FILE));
+    }
     Ok(configured_base_dir()?.join(LOCK_FILE))
 }
 
+/// `vaulty-<uid>` under `base`, created 0o700 if missing. An existing one
+/// must be a real directory owned by this user with no group or other
+/// access; anything else is refused, since whoever controls it could delete
+/// or plant the failed-attempt lock.
+#[cfg(unix)]
+fn private_lock_dir(base: &Path) -> Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+    // SAFETY: getuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    let dir = base.join(format!("vaulty-{uid}"));
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => return Ok(dir),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(anyhow!("Cannot create lock directory {}: {e}", dir.display())),
+    }
+    let meta = fs::symlink_metadata(&dir)
+        .map_err(|e| anyhow!("Cannot check lock directory {}: {e}", dir.display()))?;
+    if !meta.is_dir() {
+        return Err(anyhow!("Lock directory {} is not a directory", dir.display()));
+    }
+    if meta.uid() != uid {
+        return Err(anyhow!("Lock directory {} is owned by another user", dir.display()));
+    }
+    if meta.mode() & 0o077 != 0 {
+        return Err(anyhow!(
+            "Lock directory {} is accessible to other users (mode {:o}); expected 700",
+            dir.display(),
+            meta.mode() & 0o777
+        ));
+    }
+    Ok(dir)
+}
+
+#[cfg(not(unix))]
+fn private_lock_dir(base: &Path) -> Result<PathBuf> {
+    let dir = base.join("vaulty");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Expands a leading `$VAR` or `${VAR}` (e.g. `$XDG_RUNTIME_DIR`); relative
+/// paths are taken from the home directory.
+fn resolve_lock_dir(raw: &str) -> Result<PathBuf> {
+    let expanded = match raw.strip_prefix('$') {
+        Some(rest) => {
+            let (name, tail) = match rest.strip_prefix('{') {
+                Some(braced) => braced
+                    .split_once('}')
+                    .ok_or_else(|| anyhow!("lock_dir has an unclosed ${{"))?,
+                None => rest.split_at(rest.find('/').unwrap_or(rest.len())),
+            };
+            let value = std::env::var(name)
+                .map_err(|_| anyhow!("lock_dir uses ${name}, which is not set"))?;
+            format!("{value}{tail}")
+        }
+        None => raw.to_string(),
+    };
+    let path = PathBuf::from(expanded);
+    if path.is_absolute() {
+        return Ok(path);
+    }
+    let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not determine home directory"))?;
+    Ok(home.join(path))
+}
+
 pub fn meta_path() -> Result<PathBuf> {
     Ok(configured_base_dir()?.join(META_FILE))
 }
@@ -124,6 +719,54 @@ pub fn ensure_parent_dir(path: &Path) -> Result<()> {
     }
 }
 
+/// The vault directory and the files in it that grant more access than
+/// `file_mode`/`dir_mode` allow (group or other, by default), with the mode
+/// found. A loose mode means the vault may already have been read, so callers
+/// check this before `ensure_parent_dir` tightens it. Always empty off Unix.
+pub fn loose_vault_permissions(vault_path: &Path) -> Vec<(PathBuf, u32)> {
+    let mut loose = Vec::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let modes = permission_modes().unwrap_or(PermissionModes {
+            file: STRICT_FILE_MODE,
+            dir: STRICT_DIR_MODE,
+        });
+        let mut paths: Vec<PathBuf> = vault_path.parent().map(Path::to_path_buf).into_iter().collect();
+        paths.extend([
+            vault_path.to_path_buf(),
+            audit_log_path(vault_path),
+            trusted_revision_path(vault_path),
+        ]);
+        for path in paths {
+            if let Ok(meta) = fs::symlink_metadata(&path) {
+                let mode = meta.permissions().mode() & 0o777;
+                let allowed = if meta.is_dir() { modes.dir } else { modes.file };
+                if mode & !allowed != 0 {
+                    loose.push((path, mode));
+                }
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = vault_path;
+    loose
+}
+
+/// Like `loose_vault_permissions`, then restricts each path it found to the
+/// configured modes (0o700 directory, 0o600 files by default).
+pub fn tighten_vault_permissions(vault_path: &Path) -> Result<Vec<(PathBuf, u32)>> {
+    let loose = loose_vault_permissions(vault_path);
+    for (path, _) in &loose {
+        if path.is_dir() {
+            restrict_dir(path)?;
+        } else {
+            restrict_file(path)?;
+        }
+    }
+    Ok(loose)
+}
+
 pub fn is_wrapped_vault_file(path: &Path) -> Result<bool> {
     if !path.exists() {
         return Ok(false);
@@ -139,7 +782,51 @@ pub fn is_wrapped_vault_file(path: &Path) -> Result<bool> {
     )
 }
 
-pub fn load_vault(path: &Path, master_password: &str) -> Result<Vault> {
+/// Reads the plaintext revision header of a wrapped vault without decrypting.
+/// `None` means the file predates the header.
+pub fn peek_vault_revision(path: &Path) -> Result<Option<u64>> {
+    Ok(read_wrapped_file(path)?.revision)
+}
+
+/// KDF parameters for newly created wraps, honouring the `kdf_*` config keys.
+fn new_wrap_kdf_params() -> Result<KdfParams> {
+    let defaults = KdfParams::default();
+    let Some(cfg) = load_config()? else {
+        return Ok(defaults);
+    };
+    Ok(KdfParams {
+        m_cost: cfg.kdf_m_cost.unwrap_or(defaults.m_cost),
+        t_cost: cfg.kdf_t_cost.unwrap_or(defaults.t_cost),
+        p_cost: defaults.p_cost,
+        algorithm: cfg.kdf_algorithm,
+    })
+}
+
+/// What vault reads and writes take from `config.json` and the keyring setup,
+/// resolved once per call by the public entry points. Tests build their own so
+/// the developer's config and keyring never come into it.
+struct VaultEnv {
+    kdf: KdfParams,
+    /// Settled, never `Auto`.
+    backend: KeyringBackend,
+    modes: PermissionModes,
+}
+
+impl VaultEnv {
+    fn configured() -> Result<Self> {
+        Ok(Self {
+            kdf: new_wrap_kdf_params()?,
+            backend: resolved_keyring_backend(),
+            modes: permission_modes()?,
+        })
+    }
+
+    fn uses_revision_file(&self) -> bool {
+        self.backend == KeyringBackend::File
+    }
+}
+
+fn read_wrapped_file(path: &Path) -> Result<WrappedVaultFile> {
     let raw = fs::read_to_string(path)?;
     let wrapped: WrappedVaultFile = serde_json::from_str(&raw)?;
     if wrapped.version != VAULT_FORMAT_VERSION {
@@ -148,24 +835,422 @@ pub fn load_vault(path: &Path, master_password: &str) -> Result<Vault> {
             wrapped.version
         ));
     }
-    let salt = base64::engine::general_purpose::STANDARD
-        .decode(wrapped.kdf_salt)
-        .map_err(|e| anyhow!("Invalid vault salt encoding: {e}"))?;
-    let params = KdfParams {
-        m_cost: wrapped.kdf.m_cost,
-        t_cost: wrapped.kdf.t_cost,
-        p_cost: wrapped.kdf.p_cost,
+    Ok(wrapped)
+}
+
+fn unwrap_with(
+    kdf: &KdfSpec,
+    salt_b64: &str,
+    wrapped_key: &EncryptedVault,
+    passphrase: &str,
+) -> Result<[u8; 32]> {
+    let kek = kdf.derive(passphrase, salt_b64)?;
+    let dek = decrypt_with_key(&kek, wrapped_key)?;
+    dek.try_into()
+        .map_err(|_| anyhow!("Invalid wrapped key length in vault"))
+}
+
+pub fn join_split_passphrases(first: &str, second: &str) -> String {
+    format!("{first}{SPLIT_SEPARATOR}{second}")
+}
+
+fn split_passphrase(passphrase: &str) -> Option<(&str, &str)> {
+    passphrase.split_once(SPLIT_SEPARATOR)
+}
+
+fn xor_keys(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    for (o, (x, y)) in out.iter_mut().zip(a.iter().zip(b.iter())) {
+        *o = x ^ y;
+    }
+    out
+}
+
+/// Mixes the token's response into a master KEK for vaults enrolled with a
+/// YubiKey; others pass through unchanged.
+fn apply_token(kek: [u8; 32], challenge_b64: Option<&str>) -> Result<[u8; 32]> {
+    let Some(challenge_b64) = challenge_b64 else {
+        return Ok(kek);
     };
-    let kek = derive_key_with_params(master_password, &salt, params)?;
-    let dek = decrypt_with_key(&kek, &wrapped.wrapped_key)?;
-    let dek: [u8; 32] = dek
-        .try_into()
-        .map_err(|_| anyhow!("Invalid wrapped key length in vault"))?;
-    let decrypted = decrypt_with_key(&dek, &wrapped.vault)?;
-    let vault: Vault = serde_json::from_slice(&decrypted)?;
+    let challenge = base64::engine::general_purpose::STANDARD
+        .decode(challenge_b64)
+        .map_err(|e| anyhow!("Invalid YubiKey challenge encoding: {e}"))?;
+    token_kek(kek, &challenge)
+}
+
+#[cfg(feature = "yubikey")]
+fn token_kek(kek: [u8; 32], challenge: &[u8]) -> Result<[u8; 32]> {
+    let response = crate::yubikey::challenge_response(challenge)?;
+    Ok(crate::yubikey::mix(&kek, &response))
+}
+
+#[cfg(not(feature = "yubikey"))]
+fn token_kek(_kek: [u8; 32], _challenge: &[u8]) -> Result<[u8; 32]> {
+    Err(anyhow!(
+        "This vault needs its YubiKey; use a build with the `yubikey` feature (or the recovery passphrase)"
+    ))
+}
+
+/// A passphrase that doesn't open the vault. Unlock prompts count only this
+/// toward the attempt limit; unreadable files, corrupt data and keyring
+/// errors are reported without using up an attempt.
+#[derive(Debug)]
+pub struct WrongPassphrase;
+
+impl std::fmt::Display for WrongPassphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Wrong passphrase")
+    }
+}
+
+impl std::error::Error for WrongPassphrase {}
+
+pub fn is_wrong_passphrase(e: &anyhow::Error) -> bool {
+    e.is::<WrongPassphrase>()
+}
+
+/// A failed tag check on a key wrap means the passphrase derived the wrong
+/// key; anything else (bad base64, a missing token) stays as it was.
+fn wrong_passphrase_if_rejected(e: anyhow::Error) -> anyhow::Error {
+    if e.is::<DecryptionFailed>() {
+        WrongPassphrase.into()
+    } else {
+        e
+    }
+}
+
+fn unwrap_master(wrapped: &WrappedVaultFile, passphrase: &str) -> Result<[u8; 32]> {
+    let challenge = wrapped.yubikey_challenge.as_deref();
+    let kek = match wrapped.kdf_salt_second.as_deref() {
+        Some(salt_second) => {
+            let (first, second) = split_passphrase(passphrase)
+                .ok_or_else(|| anyhow!("This vault requires two passphrases"))?;
+            xor_keys(
+                &wrapped.kdf.derive(first, &wrapped.kdf_salt)?,
+                &wrapped.kdf.derive(second, salt_second)?,
+            )
+        }
+        None => wrapped.kdf.derive(passphrase, &wrapped.kdf_salt)?,
+    };
+    let kek = apply_token(kek, challenge)?;
+    let dek = decrypt_with_key(&kek, &wrapped.wrapped_key).map_err(wrong_passphrase_if_rejected)?;
+    dek.try_into()
+        .map_err(|_| anyhow!("Invalid wrapped key length in vault"))
+}
+
+/// Fresh salt(s) and master wrap of `dek`. A passphrase containing
+/// `SPLIT_SEPARATOR` produces a split-knowledge wrap; `challenge_b64` adds the
+/// YubiKey response.
+fn wrap_for_master(
+    master_password: &str,
+    dek: &[u8; 32],
+    params: KdfParams,
+    challenge_b64: Option<&str>,
+) -> Result<(String, Option<String>, EncryptedVault)> {
+    let mut salt = [0u8; KDF_SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let encode = |s: &[u8]| base64::engine::general_purpose::STANDARD.encode(s);
+    match split_passphrase(master_password) {
+        Some((first, second)) => {
+            let mut salt_second = [0u8; KDF_SALT_LEN];
+            OsRng.fill_bytes(&mut salt_second);
+            let kek = xor_keys(
+                &derive_key_with_params(first, &salt, params)?,
+                &derive_key_with_params(second, &salt_second, params)?,
+            );
+            let kek = apply_token(kek, challenge_b64)?;
+            Ok((encode(&salt), Some(encode(&salt_second)), encrypt_with_key(&kek, dek)?))
+        }
+        None => {
+            let kek = derive_key_with_params(master_password, &salt, params)?;
+            let kek = apply_token(kek, challenge_b64)?;
+            Ok((encode(&salt), None, encrypt_with_key(&kek, dek)?))
+        }
+    }
+}
+
+/// Unwraps the DEK with the master wrap, falling back to the recovery wrap.
+fn unwrap_dek(wrapped: &WrappedVaultFile, passphrase: &str) -> Result<[u8; 32]> {
+    let master = unwrap_master(wrapped, passphrase);
+    match (&master, &wrapped.wrapped_key_recovery) {
+        // Report the master error when both fail, e.g. a missing token.
+        (Err(_), Some(recovery)) => {
+            unwrap_with(&recovery.kdf, &recovery.kdf_salt, &recovery.wrapped_key, passphrase)
+                .or(master)
+        }
+        _ => master,
+    }
+}
+
+pub fn load_vault(path: &Path, master_password: &str) -> Result<Vault> {
+    load_vault_in(path, master_password, &VaultEnv::configured()?)
+}
+
+fn load_vault_in(path: &Path, master_password: &str, env: &VaultEnv) -> Result<Vault> {
+    let wrapped = read_wrapped_file(path)?;
+    let dek = unwrap_dek(&wrapped, master_password)?;
+    let decrypted = decrypt_with_key(&dek, &wrapped.vault).map_err(|e| {
+        if e.is::<DecryptionFailed>() {
+            anyhow!("Vault data failed to decrypt under its unwrapped key; the file may be corrupt")
+        } else {
+            e
+        }
+    })?;
+    let vault = decode_vault(&decrypted, wrapped.compression)?;
+    if env.uses_revision_file() {
+        load_fallback_revision(path, &dek, vault.revision, env)?;
+    }
     Ok(vault)
 }
 
+fn has_header_field(path: &Path, field: &str) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    let raw = fs::read_to_string(path)?;
+    let value: serde_json::Value = match serde_json::from_str(&raw) {
+        Ok(v) => v,
+        Err(_) => return Ok(false),
+    };
+    Ok(value.get(field).is_some_and(|v| !v.is_null()))
+}
+
+pub fn has_recovery_key(path: &Path) -> Result<bool> {
+    has_header_field(path, "wrapped_key_recovery")
+}
+
+pub fn is_split_vault(path: &Path) -> Result<bool> {
+    has_header_field(path, "kdf_salt_second")
+}
+
+/// Adds or rotates the recovery wrap. `master_password` must open the vault
+/// (either wrap); the vault contents are left untouched.
+pub fn set_recovery_passphrase(path: &Path, master_password: &str, recovery: &str) -> Result<()> {
+    let mut wrapped = read_wrapped_file(path)?;
+    let dek = unwrap_dek(&wrapped, master_password)?;
+    let env = VaultEnv::configured()?;
+    let mut salt = [0u8; KDF_SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let kek = derive_key_with_params(recovery, &salt, env.kdf)?;
+    wrapped.wrapped_key_recovery = Some(RecoveryWrap {
+        kdf: KdfSpec::from_params(env.kdf),
+        kdf_salt: base64::engine::general_purpose::STANDARD.encode(salt),
+        wrapped_key: encrypt_with_key(&kek, &dek)?,
+    });
+    write_wrapped_file(path, &wrapped, &env)
+}
+
+fn audit_log_path(vault_path: &Path) -> PathBuf {
+    vault_path.with_file_name(AUDIT_LOG_FILE)
+}
+
+/// Where [`rotate_dek`] stages the re-encrypted `audit.log`.
+fn pending_audit_log_path(vault_path: &Path) -> PathBuf {
+    vault_path.with_file_name(format!("{AUDIT_LOG_FILE}.rotating"))
+}
+
+/// Finishes a data key rotation that stopped between writing the vault and
+/// replacing `audit.log`. A staged log whose records open under `dek` belongs
+/// to the vault on disk and replaces the old one; otherwise the vault was
+/// never rewritten and the staged copy is dropped.
+fn settle_audit_rotation(vault_path: &Path, dek: &[u8; 32]) -> Result<()> {
+    let pending = pending_audit_log_path(vault_path);
+    if !pending.exists() {
+        return Ok(());
+    }
+    let first = BufReader::new(fs::File::open(&pending)?)
+        .lines()
+        .map_while(Result::ok)
+        .find(|line| !line.trim().is_empty());
+    let current = first.is_none_or(|line| {
+        serde_json::from_str::<EncryptedVault>(&line)
+            .ok()
+            .and_then(|record| decrypt_with_key(dek, &record).ok())
+            .is_some_and(|mut plaintext| {
+                plaintext.zeroize();
+                true
+            })
+    });
+    if current {
+        fs::rename(&pending, audit_log_path(vault_path))?;
+    } else {
+        fs::remove_file(&pending)?;
+    }
+    Ok(())
+}
+
+pub fn has_yubikey(path: &Path) -> Result<bool> {
+    has_header_field(path, "yubikey_challenge")
+}
+
+/// The DEK must stay put while anything else is encrypted under it: the
+/// recovery wrap, the records already in `audit.log`, or the fallback trusted
+/// revision. Token vaults keep it too, so saves re-encrypt without asking the
+/// token again.
+fn keeps_dek(path: &Path) -> Result<bool> {
+    Ok(path.exists()
+        && (has_recovery_key(path)?
+            || has_yubikey(path)?
+            || audit_log_path(path).exists()
+            || trusted_revision_path(path).exists()))
+}
+
+/// Enrolls the inserted YubiKey: a fresh challenge is stored and the master
+/// wrap is redone with its response mixed in.
+#[cfg(feature = "yubikey")]
+pub fn enroll_yubikey(path: &Path, master_password: &str) -> Result<()> {
+    let mut wrapped = read_wrapped_file(path)?;
+    if wrapped.yubikey_challenge.is_some() {
+        return Err(anyhow!("A YubiKey is already enrolled for this vault"));
+    }
+    let dek = unwrap_master(&wrapped, master_password)?;
+    let mut challenge = [0u8; crate::yubikey::CHALLENGE_LEN];
+    OsRng.fill_bytes(&mut challenge);
+    let challenge_b64 = base64::engine::general_purpose::STANDARD.encode(challenge);
+    let env = VaultEnv::configured()?;
+    let (kdf_salt, kdf_salt_second, wrapped_key) =
+        wrap_for_master(master_password, &dek, env.kdf, Some(&challenge_b64))?;
+    wrapped.kdf = KdfSpec::from_params(env.kdf);
+    wrapped.kdf_salt = kdf_salt;
+    wrapped.kdf_salt_second = kdf_salt_second;
+    wrapped.wrapped_key = wrapped_key;
+    wrapped.yubikey_challenge = Some(challenge_b64);
+    write_wrapped_file(path, &wrapped, &env)
+}
+
+/// Re-wraps the fixed DEK under a new master passphrase when the DEK is kept
+/// fixed (see `keeps_dek`). Returns `false` (and does nothing) otherwise, since
+/// plain saves already generate a fresh DEK for the new passphrase.
+pub fn rewrap_master_key(path: &Path, current: &str, new_master: &str) -> Result<bool> {
+    if !keeps_dek(path)? {
+        return Ok(false);
+    }
+    let mut wrapped = read_wrapped_file(path)?;
+    let dek = unwrap_dek(&wrapped, current)?;
+    let env = VaultEnv::configured()?;
+    let (kdf_salt, kdf_salt_second, wrapped_key) =
+        wrap_for_master(new_master, &dek, env.kdf, wrapped.yubikey_challenge.as_deref())?;
+    wrapped.kdf = KdfSpec::from_params(env.kdf);
+    wrapped.kdf_salt = kdf_salt;
+    wrapped.kdf_salt_second = kdf_salt_second;
+    wrapped.wrapped_key = wrapped_key;
+    write_wrapped_file(path, &wrapped, &env)?;
+    Ok(true)
+}
+
+/// Replaces the DEK with a fresh one and re-encrypts everything under it: the
+/// vault (saved at `vault.revision`), every `audit.log` record and the fallback
+/// trusted revision. The master wrap is redone, keeping any YubiKey
+/// enrollment; a recovery wrap needs `recovery` to be redone, since the old
+/// one would no longer open anything. Each file is replaced atomically, the
+/// vault file first.
+pub fn rotate_dek(
+    path: &Path,
+    vault: &Vault,
+    master_password: &str,
+    recovery: Option<&str>,
+    events: &[AuditEvent],
+) -> Result<()> {
+    rotate_dek_in(path, vault, master_password, recovery, events, &VaultEnv::configured()?)
+}
+
+fn rotate_dek_in(
+    path: &Path,
+    vault: &Vault,
+    master_password: &str,
+    recovery: Option<&str>,
+    events: &[AuditEvent],
+    env: &VaultEnv,
+) -> Result<()> {
+    let mut wrapped = read_wrapped_file(path)?;
+    let mut old_dek = unwrap_master(&wrapped, master_password)
+        .map_err(|e| anyhow!("The master passphrase is needed to rotate the data key: {e}"))?;
+    let mut dek = [0u8; 32];
+    OsRng.fill_bytes(&mut dek);
+    let result = (|| -> Result<()> {
+        settle_audit_rotation(path, &old_dek)?;
+        if let Some(old) = &wrapped.wrapped_key_recovery {
+            let recovery =
+                recovery.ok_or_else(|| anyhow!("This vault has a recovery passphrase; it is needed too"))?;
+            let mut check = unwrap_with(&old.kdf, &old.kdf_salt, &old.wrapped_key, recovery)
+                .map_err(|_| anyhow!("Recovery passphrase is incorrect"))?;
+            check.zeroize();
+            let mut salt = [0u8; KDF_SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            let kek = derive_key_with_params(recovery, &salt, env.kdf)?;
+            wrapped.wrapped_key_recovery = Some(RecoveryWrap {
+                kdf: KdfSpec::from_params(env.kdf),
+                kdf_salt: base64::engine::general_purpose::STANDARD.encode(salt),
+                wrapped_key: encrypt_with_key(&kek, &dek)?,
+            });
+        }
+        // The re-encrypted log waits beside the old one until the vault under
+        // the new key is on disk; settle_audit_rotation finishes the swap if
+        // we stop in between.
+        let log = reencrypt_audit_log(path, &old_dek, &dek)?;
+        let pending = pending_audit_log_path(path);
+        if let Some(log) = &log {
+            atomic_write(&pending, log.as_bytes())?;
+            set_file_mode(&pending, env.modes.file)?;
+        }
+        let (kdf_salt, kdf_salt_second, wrapped_key) =
+            wrap_for_master(master_password, &dek, env.kdf, wrapped.yubikey_challenge.as_deref())?;
+        wrapped.kdf = KdfSpec::from_params(env.kdf);
+        wrapped.kdf_salt = kdf_salt;
+        wrapped.kdf_salt_second = kdf_salt_second;
+        wrapped.wrapped_key = wrapped_key;
+        let compression = configured_compression(vault);
+        let plaintext = encode_vault(vault, compression)?;
+        wrapped.vault = encrypt_with_key(&dek, &plaintext)?;
+        wrapped.compression = compression;
+        wrapped.revision = Some(vault.revision);
+        write_wrapped_file(path, &wrapped, env)?;
+        if log.is_some() {
+            fs::rename(&pending, audit_log_path(path))?;
+        }
+        if env.uses_revision_file() || trusted_revision_path(path).exists() {
+            write_fallback_revision(path, &dek, vault.revision, env)?;
+        }
+        append_audit_events(path, &dek, events, env)
+    })();
+    old_dek.zeroize();
+    dek.zeroize();
+    result
+}
+
+/// `audit.log` with every record moved from `old` to `new`, or `None` when
+/// there is no log yet. Fails on the first record that doesn't open.
+fn reencrypt_audit_log(vault_path: &Path, old: &[u8; 32], new: &[u8; 32]) -> Result<Option<String>> {
+    let path = audit_log_path(vault_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut lines = String::new();
+    for (idx, line) in BufReader::new(fs::File::open(&path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: EncryptedVault = serde_json::from_str(&line)
+            .map_err(|e| anyhow!("audit.log line {} is malformed: {e}", idx + 1))?;
+        let mut plaintext = decrypt_with_key(old, &record)
+            .map_err(|_| anyhow!("audit.log line {} failed to decrypt", idx + 1))?;
+        let record = encrypt_with_key(new, &plaintext);
+        plaintext.zeroize();
+        lines.push_str(&serde_json::to_string(&record?)?);
+        lines.push('\n');
+    }
+    Ok(Some(lines))
+}
+
+fn write_wrapped_file(path: &Path, wrapped: &WrappedVaultFile, env: &VaultEnv) -> Result<()> {
+    let serialized = serde_json::to_string_pretty(wrapped)?;
+    ensure_free_space(path, serialized.len() as u64)?;
+    atomic_write(path, serialized.as_bytes())?;
+    set_file_mode(path, env.modes.file)?;
+    Ok(())
+}
+
 pub fn load_vault_with_key(path: &Path, key: &[u8; 32]) -> Result<Vault> {
     let raw = fs::read_to_string(path)?;
     let enc: EncryptedVault = serde_json::from_str(&raw)?;
@@ -174,39 +1259,214 @@ pub fn load_vault_with_key(path: &Path, key: &[u8; 32]) -> Result<Vault> {
     Ok(vault)
 }
 
+/// Compression applied to the vault JSON before encryption, picked by the
+/// `vault_compression` vault setting. Only zlib for now.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum VaultCompression {
+    #[default]
+    None,
+    Zlib,
+}
+
+/// The vault's compression setting as the flag its file records: `None` when
+/// saving uncompressed.
+fn configured_compression(vault: &Vault) -> Option<VaultCompression> {
+    let configured = vault.settings.as_ref().map(|s| s.vault_compression).unwrap_or_default();
+    (configured != VaultCompression::None).then_some(configured)
+}
+
+/// Vault JSON ready for encryption, compressed as `compression` says.
+fn encode_vault(vault: &Vault, compression: Option<VaultCompression>) -> Result<Vec<u8>> {
+    let mut json = serde_json::to_vec(vault)?;
+    match compression {
+        None | Some(VaultCompression::None) => Ok(json),
+        Some(VaultCompression::Zlib) => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&json)?;
+            json.zeroize();
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+fn decode_vault(plaintext: &[u8], compression: Option<VaultCompression>) -> Result<Vault> {
+    match compression {
+        None | Some(VaultCompression::None) => Ok(serde_json::from_slice(plaintext)?),
+        Some(VaultCompression::Zlib) => {
+            let mut json = Vec::new();
+            ZlibDecoder::new(plaintext)
+                .read_to_end(&mut json)
+                .map_err(|e| anyhow!("Vault data failed to decompress: {e}"))?;
+            let vault = serde_json::from_slice(&json);
+            json.zeroize();
+            Ok(vault?)
+        }
+    }
+}
+
 pub fn save_vault(path: &Path, vault: &Vault, master_password: &str) -> Result<()> {
-    let mut salt = [0u8; KDF_SALT_LEN];
-    OsRng.fill_bytes(&mut salt);
-    let params = KdfParams::default();
-    let kek = derive_key_with_params(master_password, &salt, params)?;
+    save_vault_logged(path, vault, master_password, &[])
+}
+
+/// Saves the vault, then appends `events` to `audit.log` under the same DEK.
+pub fn save_vault_logged(
+    path: &Path,
+    vault: &Vault,
+    master_password: &str,
+    events: &[AuditEvent],
+) -> Result<()> {
+    save_vault_logged_in(path, vault, master_password, events, &VaultEnv::configured()?)
+}
 
+fn save_vault_logged_in(
+    path: &Path,
+    vault: &Vault,
+    master_password: &str,
+    events: &[AuditEvent],
+    env: &VaultEnv,
+) -> Result<()> {
+    if keeps_dek(path)? {
+        // Keep the DEK (and both wraps) so the recovery passphrase and earlier
+        // audit records stay readable.
+        let mut wrapped = read_wrapped_file(path)?;
+        let dek = unwrap_dek(&wrapped, master_password)?;
+        let compression = configured_compression(vault);
+        let plaintext = encode_vault(vault, compression)?;
+        wrapped.vault = encrypt_with_key(&dek, &plaintext)?;
+        wrapped.compression = compression;
+        wrapped.revision = Some(vault.revision);
+        write_wrapped_file(path, &wrapped, env)?;
+        if env.uses_revision_file() {
+            write_fallback_revision(path, &dek, vault.revision, env)?;
+        }
+        return append_audit_events(path, &dek, events, env);
+    }
+
+    let params = env.kdf;
     let mut dek = [0u8; 32];
     OsRng.fill_bytes(&mut dek);
 
-    let wrapped_key = encrypt_with_key(&kek, &dek)?;
-    let plaintext = serde_json::to_vec(vault)?;
+    let (kdf_salt, kdf_salt_second, wrapped_key) =
+        wrap_for_master(master_password, &dek, params, None)?;
+    let compression = configured_compression(vault);
+    let plaintext = encode_vault(vault, compression)?;
     let enc_vault = encrypt_with_key(&dek, &plaintext)?;
     let wrapped = WrappedVaultFile {
         version: VAULT_FORMAT_VERSION,
-        kdf: KdfSpec {
-            m_cost: params.m_cost,
-            t_cost: params.t_cost,
-            p_cost: params.p_cost,
-        },
-        kdf_salt: base64::engine::general_purpose::STANDARD.encode(salt),
+        kdf: KdfSpec::from_params(params),
+        kdf_salt,
+        kdf_salt_second,
         wrapped_key,
         vault: enc_vault,
+        revision: Some(vault.revision),
+        wrapped_key_recovery: None,
+        yubikey_challenge: None,
+        compression,
     };
-    let serialized = serde_json::to_string_pretty(&wrapped)?;
-    atomic_write(path, serialized.as_bytes())?;
-    restrict_file(path)?;
+    write_wrapped_file(path, &wrapped, env)?;
+    if env.uses_revision_file() {
+        write_fallback_revision(path, &dek, vault.revision, env)?;
+    }
+    append_audit_events(path, &dek, events, env)
+}
+
+/// Records `events` without saving the vault, for actions that read it but
+/// leave it unchanged, such as exports.
+pub fn log_audit_events(vault_path: &Path, master_password: &str, events: &[AuditEvent]) -> Result<()> {
+    log_audit_events_in(vault_path, master_password, events, &VaultEnv::configured()?)
+}
+
+fn log_audit_events_in(vault_path: &Path, master_password: &str, events: &[AuditEvent], env: &VaultEnv) -> Result<()> {
+    let mut dek = unwrap_dek(&read_wrapped_file(vault_path)?, master_password)?;
+    let logged = append_audit_events(vault_path, &dek, events, env);
+    dek.zeroize();
+    logged
+}
+
+/// Appends one encrypted record per line; earlier lines are never rewritten.
+fn append_audit_events(vault_path: &Path, dek: &[u8; 32], events: &[AuditEvent], env: &VaultEnv) -> Result<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    settle_audit_rotation(vault_path, dek)?;
+    let path = audit_log_path(vault_path);
+    let mut lines = String::new();
+    for event in events {
+        let record = encrypt_with_key(dek, &serde_json::to_vec(event)?)?;
+        lines.push_str(&serde_json::to_string(&record)?);
+        lines.push('\n');
+    }
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    set_file_mode(&path, env.modes.file)?;
+    file.write_all(lines.as_bytes())?;
+    file.sync_all()?;
     Ok(())
 }
 
+/// Newest `vault-<unix time>.json` stamp in `dir`, so the backup schedule is
+/// read off the backups themselves instead of being recorded anywhere.
+pub fn latest_backup_at(dir: &Path) -> Option<u64> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            name.to_str()?.strip_prefix("vault-")?.strip_suffix(".json")?.parse().ok()
+        })
+        .max()
+}
+
+/// Copies the vault file (still encrypted) and its audit log into `dir` under
+/// timestamped names. Returns the path of the vault copy.
+pub fn backup_vault(vault_path: &Path, dir: &Path) -> Result<PathBuf> {
+    if !dir.exists() {
+        fs::create_dir_all(dir)?;
+        restrict_dir(dir)?;
+    }
+    let stamp = unix_now()?;
+    let target = dir.join(format!("vault-{stamp}.json"));
+    // Backups are never replaced, not even one taken earlier in the same second.
+    if target.exists() {
+        return Err(anyhow!("{} already exists; not replacing an earlier backup", target.display()));
+    }
+    atomic_write(&target, &fs::read(vault_path)?)?;
+    restrict_file(&target)?;
+    let log = audit_log_path(vault_path);
+    if log.exists() {
+        let log_target = dir.join(format!("audit-{stamp}.log"));
+        atomic_write(&log_target, &fs::read(&log)?)?;
+        restrict_file(&log_target)?;
+    }
+    Ok(target)
+}
+
+/// Decrypts every `audit.log` record, oldest first.
+pub fn read_audit_log(vault_path: &Path, master_password: &str) -> Result<Vec<AuditEvent>> {
+    let path = audit_log_path(vault_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let dek = unwrap_dek(&read_wrapped_file(vault_path)?, master_password)?;
+    settle_audit_rotation(vault_path, &dek)?;
+    let mut events = Vec::new();
+    for (idx, line) in BufReader::new(fs::File::open(&path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: EncryptedVault = serde_json::from_str(&line)
+            .map_err(|e| anyhow!("audit.log line {} is malformed: {e}", idx + 1))?;
+        let plaintext = decrypt_with_key(&dek, &record)
+            .map_err(|_| anyhow!("audit.log line {} failed to decrypt", idx + 1))?;
+        events.push(serde_json::from_slice(&plaintext)?);
+    }
+    Ok(events)
+}
+
 pub fn load_vault_legacy(path: &Path, master_password: &str) -> Result<Vault> {
     let raw = fs::read_to_string(path)?;
     let enc: EncryptedVault = serde_json::from_str(&raw)?;
-    let decrypted = decrypt_with_password(master_password, &enc)?;
+    let decrypted = decrypt_with_password(master_password, &enc).map_err(wrong_passphrase_if_rejected)?;
     let vault: Vault = serde_json::from_slice(&decrypted)?;
     Ok(vault)
 }
@@ -218,20 +1478,46 @@ fn unix_now() -> Result<u64> {
     Ok(now.as_secs())
 }
 
-pub fn load_lock(path: &Path) -> Result<Option<u64>> {
+/// Decrypted view written by `--export`. Borrowing keeps the secrets from being
+/// copied; the revision is left out since it changes on every save.
+#[derive(Serialize)]
+struct VaultExport<'a> {
+    entries: Vec<&'a Entry>,
+    notes: Vec<&'a Note>,
+}
+
+/// Plaintext, pretty-printed JSON of the vault in a canonical order: entries
+/// by service, email, then id; notes by title, then id. Exporting the same
+/// data twice gives the same bytes, so exports diff cleanly.
+pub fn export_vault_json(vault: &Vault) -> Result<String> {
+    let mut entries: Vec<&Entry> = vault.entries.iter().collect();
+    entries.sort_by(|a, b| (&a.name, &a.email, &a.id).cmp(&(&b.name, &b.email, &b.id)));
+    let mut notes: Vec<&Note> = vault.notes.iter().collect();
+    notes.sort_by(|a, b| (&a.title, &a.id).cmp(&(&b.title, &b.id)));
+    Ok(serde_json::to_string_pretty(&VaultExport { entries, notes })?)
+}
+
+fn read_lock_state(path: &Path) -> Result<Option<LockState>> {
     if !path.exists() {
         return Ok(None);
     }
     let raw = fs::read_to_string(path)?;
-    let lock: LockState = serde_json::from_str(&raw)?;
-    Ok(Some(lock.unlock_at))
+    Ok(Some(serde_json::from_str(&raw)?))
+}
+
+pub fn load_lock(path: &Path) -> Result<Option<u64>> {
+    Ok(read_lock_state(path)?.map(|lock| lock.unlock_at))
 }
 
 pub fn save_lock(path: &Path, unlock_at: u64) -> Result<()> {
-    let lock = LockState { unlock_at };
-    let data = serde_json::to_string_pretty(&lock)?;
+    let lock = LockState { unlock_at, locked_at: Some(unix_now()?), failed_attempts: 0 };
+    write_lock_state(path, &lock, permission_modes()?.file)
+}
+
+fn write_lock_state(path: &Path, lock: &LockState, file_mode: u32) -> Result<()> {
+    let data = serde_json::to_string_pretty(lock)?;
     atomic_write(path, data.as_bytes())?;
-    restrict_file(path)?;
+    set_file_mode(path, file_mode)?;
     Ok(())
 }
 
@@ -243,13 +1529,21 @@ pub fn clear_lock(path: &Path) -> Result<()> {
 }
 
 pub fn ensure_lock_not_active(lock_path: &Path) -> Result<()> {
-    if let Some(until) = load_lock(lock_path)? {
+    if let Some(lock) = read_lock_state(lock_path)? {
         let now = unix_now()?;
-        if now < until {
-            let remaining = until - now;
+        if let Some(remaining) = lock.remaining(now) {
+            if lock.rolled_back(now) {
+                // Restart the lock from the rolled-back clock so it still takes
+                // the full duration of real time to expire.
+                write_lock_state(
+                    lock_path,
+                    &LockState { unlock_at: now + remaining, locked_at: Some(now), failed_attempts: 0 },
+                    permission_modes()?.file,
+                )?;
+            }
             println!("Vault is locked due to failed attempts. Try again in {remaining} seconds.");
             std::process::exit(1);
-        } else {
+        } else if lock.unlock_at != 0 {
             // expired; clear it
             clear_lock(lock_path)?;
         }
@@ -257,6 +1551,31 @@ pub fn ensure_lock_not_active(lock_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Counts a rejected passphrase from a one-shot command, which only gets one
+/// try per run. Reaching `max` in a row sets the lock (and exits) like the
+/// unlock screen does; otherwise returns the attempts left.
+pub fn record_failed_attempt(lock_path: &Path, max: u8, duration_secs: u64) -> Result<u8> {
+    record_failed_attempt_in(lock_path, max, duration_secs, permission_modes()?.file)
+}
+
+fn record_failed_attempt_in(lock_path: &Path, max: u8, duration_secs: u64, file_mode: u32) -> Result<u8> {
+    let mut lock = read_lock_state(lock_path)?.unwrap_or_default();
+    lock.failed_attempts = lock.failed_attempts.saturating_add(1);
+    if lock.failed_attempts >= max {
+        set_lock(lock_path, duration_secs)?;
+    }
+    write_lock_state(lock_path, &lock, file_mode)?;
+    Ok(max - lock.failed_attempts)
+}
+
+/// Forgets counted failures after an accepted passphrase.
+pub fn clear_failed_attempts(lock_path: &Path) -> Result<()> {
+    match read_lock_state(lock_path)? {
+        Some(lock) if lock.unlock_at == 0 => clear_lock(lock_path),
+        _ => Ok(()),
+    }
+}
+
 pub fn set_lock(lock_path: &Path, duration_secs: u64) -> Result<()> {
     let unlock_at = unix_now()? + duration_secs;
     save_lock(lock_path, unlock_at)?;
@@ -280,23 +1599,172 @@ pub fn save_meta(path: &Path, meta: &Meta) -> Result<()> {
     Ok(())
 }
 
-pub fn load_wrapped_key() -> Result<Option<[u8; 32]>> {
-    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
-    match entry.get_password() {
-        Ok(stored) => {
-            let bytes = base64::engine::general_purpose::STANDARD
-                .decode(stored)
-                .map_err(|e| anyhow!("Failed to decode wrapped key: {e}"))?;
-            let arr: [u8; 32] = bytes
-                .try_into()
-                .map_err(|_| anyhow!("Stored wrapped key has invalid length"))?;
-            Ok(Some(arr))
+/// Where the trusted revision and the legacy wrapped key live, picked by the
+/// `keyring_backend` config key.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyringBackend {
+    /// The OS keyring when it answers, otherwise `File`.
+    #[default]
+    Auto,
+    /// The OS keyring (Secret Service, macOS Keychain, Windows Credential
+    /// Manager); errors are reported rather than worked around.
+    SecretService,
+    /// `TRUSTED_REVISION_FILE` next to the vault, encrypted under the DEK.
+    File,
+    /// Nothing is stored, which turns rollback protection off.
+    None,
+}
+
+impl KeyringBackend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeyringBackend::Auto => "auto",
+            KeyringBackend::SecretService => "secret-service",
+            KeyringBackend::File => "file",
+            KeyringBackend::None => "none",
+        }
+    }
+}
+
+trait SecretStore: Sync {
+    fn load_trusted_revision(&self) -> Result<Option<u64>>;
+    fn store_trusted_revision(&self, revision: u64) -> Result<()>;
+    /// Pre-v2 installs kept their vault key here; only migration reads it.
+    fn load_wrapped_key(&self) -> Result<Option<[u8; 32]>>;
+    fn clear_wrapped_key(&self) -> Result<bool>;
+}
+
+struct KeyringStore;
+struct FileStore;
+struct NoStore;
+
+impl SecretStore for KeyringStore {
+    fn load_trusted_revision(&self) -> Result<Option<u64>> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_REV_USER)?;
+        match entry.get_password() {
+            Ok(stored) => {
+                let parsed = stored
+                    .parse::<u64>()
+                    .map_err(|e| anyhow!("Invalid trusted revision in keyring: {e}"))?;
+                Ok(Some(parsed))
+            }
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow!("Keyring read error: {e}")),
+        }
+    }
+
+    fn store_trusted_revision(&self, revision: u64) -> Result<()> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_REV_USER)?;
+        entry
+            .set_password(&revision.to_string())
+            .map_err(|e| anyhow!("Keyring write error: {e}"))
+    }
+
+    fn load_wrapped_key(&self) -> Result<Option<[u8; 32]>> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+        match entry.get_password() {
+            Ok(stored) => {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(stored)
+                    .map_err(|e| anyhow!("Failed to decode wrapped key: {e}"))?;
+                let arr: [u8; 32] = bytes
+                    .try_into()
+                    .map_err(|_| anyhow!("Stored wrapped key has invalid length"))?;
+                Ok(Some(arr))
+            }
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow!("Keyring read error: {e}")),
+        }
+    }
+
+    fn clear_wrapped_key(&self) -> Result<bool> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+        match entry.delete_password() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(anyhow!("Keyring delete error: {e}")),
+        }
+    }
+}
+
+/// The file itself is read and written by `load_vault` and the saves, which
+/// hold the DEK; this side only reports what the last unlock read.
+impl SecretStore for FileStore {
+    fn load_trusted_revision(&self) -> Result<Option<u64>> {
+        Ok(*FALLBACK_REVISION.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn store_trusted_revision(&self, revision: u64) -> Result<()> {
+        *FALLBACK_REVISION.lock().unwrap_or_else(|e| e.into_inner()) = Some(revision);
+        Ok(())
+    }
+
+    fn load_wrapped_key(&self) -> Result<Option<[u8; 32]>> {
+        Ok(None)
+    }
+
+    fn clear_wrapped_key(&self) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+impl SecretStore for NoStore {
+    fn load_trusted_revision(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    fn store_trusted_revision(&self, _revision: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn load_wrapped_key(&self) -> Result<Option<[u8; 32]>> {
+        Ok(None)
+    }
+
+    fn clear_wrapped_key(&self) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Whether the OS keyring answers at all. On headless servers without a
+/// Secret Service it doesn't.
+fn keyring_available() -> bool {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_REV_USER).is_ok_and(|entry| {
+        matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry))
+    })
+}
+
+/// The configured backend with `Auto` settled, resolved once per process.
+/// A config that can't be read counts as `Auto`.
+pub fn resolved_keyring_backend() -> KeyringBackend {
+    static RESOLVED: OnceLock<KeyringBackend> = OnceLock::new();
+    *RESOLVED.get_or_init(|| {
+        let configured = load_config()
+            .ok()
+            .flatten()
+            .map(|cfg| cfg.keyring_backend)
+            .unwrap_or_default();
+        match configured {
+            KeyringBackend::Auto if keyring_available() => KeyringBackend::SecretService,
+            KeyringBackend::Auto => KeyringBackend::File,
+            other => other,
         }
-        Err(keyring::Error::NoEntry) => Ok(None),
-        Err(e) => Err(anyhow!("Keyring read error: {e}")),
+    })
+}
+
+fn secret_store() -> &'static dyn SecretStore {
+    match resolved_keyring_backend() {
+        KeyringBackend::Auto | KeyringBackend::SecretService => &KeyringStore,
+        KeyringBackend::File => &FileStore,
+        KeyringBackend::None => &NoStore,
     }
 }
 
+pub fn load_wrapped_key() -> Result<Option<[u8; 32]>> {
+    secret_store().load_wrapped_key()
+}
+
 pub fn store_wrapped_key(key: &[u8; 32]) -> Result<()> {
     let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
     let encoded = base64::engine::general_purpose::STANDARD.encode(key);
@@ -305,25 +1773,90 @@ pub fn store_wrapped_key(key: &[u8; 32]) -> Result<()> {
         .map_err(|e| anyhow!("Keyring write error: {e}"))
 }
 
-pub fn load_trusted_revision() -> Result<Option<u64>> {
-    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_REV_USER)?;
-    match entry.get_password() {
-        Ok(stored) => {
-            let parsed = stored
-                .parse::<u64>()
-                .map_err(|e| anyhow!("Invalid trusted revision in keyring: {e}"))?;
-            Ok(Some(parsed))
-        }
-        Err(keyring::Error::NoEntry) => Ok(None),
-        Err(e) => Err(anyhow!("Keyring read error: {e}")),
+pub fn clear_wrapped_key() -> Result<bool> {
+    secret_store().clear_wrapped_key()
+}
+
+/// Trusted revision read from the fallback file by the last `load_vault`.
+/// The file needs the DEK, so `load_trusted_revision` can only report what an
+/// unlock has already read.
+static FALLBACK_REVISION: Mutex<Option<u64>> = Mutex::new(None);
+
+pub fn trusted_revision_path(vault_path: &Path) -> PathBuf {
+    vault_path.with_file_name(TRUSTED_REVISION_FILE)
+}
+
+/// Caches the fallback file's revision for `load_trusted_revision`, creating
+/// or advancing the file when the vault is newer. An older vault leaves the
+/// file alone so the rollback check still sees the higher number. A file that
+/// doesn't open under this DEK (left over from another vault) counts as
+/// missing, the same as a deleted one.
+fn load_fallback_revision(vault_path: &Path, dek: &[u8; 32], revision: u64, env: &VaultEnv) -> Result<()> {
+    let path = trusted_revision_path(vault_path);
+    let stored = fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_json::from_str::<EncryptedVault>(&raw).ok())
+        .and_then(|enc| decrypt_with_key(dek, &enc).ok())
+        .and_then(|plain| String::from_utf8(plain).ok())
+        .and_then(|text| text.parse::<u64>().ok());
+    *FALLBACK_REVISION.lock().unwrap_or_else(|e| e.into_inner()) = stored;
+    if stored.is_none_or(|stored| revision > stored) {
+        write_fallback_revision(vault_path, dek, revision, env)?;
     }
+    Ok(())
+}
+
+fn write_fallback_revision(vault_path: &Path, dek: &[u8; 32], revision: u64, env: &VaultEnv) -> Result<()> {
+    let path = trusted_revision_path(vault_path);
+    let enc = encrypt_with_key(dek, revision.to_string().as_bytes())?;
+    atomic_write(&path, serde_json::to_string(&enc)?.as_bytes())?;
+    set_file_mode(&path, env.modes.file)
+}
+
+pub fn load_trusted_revision() -> Result<Option<u64>> {
+    secret_store().load_trusted_revision()
 }
 
 pub fn store_trusted_revision(revision: u64) -> Result<()> {
-    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_REV_USER)?;
-    entry
-        .set_password(&revision.to_string())
-        .map_err(|e| anyhow!("Keyring write error: {e}"))
+    secret_store().store_trusted_revision(revision)
+}
+
+/// Last failed trusted-revision update, kept for the UI to show.
+static TRUSTED_REVISION_FAILURE: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn note_trusted_revision_failure(e: &anyhow::Error) {
+    *TRUSTED_REVISION_FAILURE.lock().unwrap_or_else(|e| e.into_inner()) = Some(e.to_string());
+}
+
+pub fn take_trusted_revision_failure() -> Option<String> {
+    TRUSTED_REVISION_FAILURE.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+/// Rollback check for a just-loaded `revision`: older than the trusted value
+/// is an error, newer moves the trusted value up, and a missing one is seeded.
+/// A store that can't be read doesn't block the unlock.
+pub fn verify_trusted_revision(revision: u64) -> Result<()> {
+    check_revision(secret_store(), revision)
+}
+
+fn check_revision(store: &dyn SecretStore, revision: u64) -> Result<()> {
+    let trusted = match store.load_trusted_revision() {
+        Ok(v) => v,
+        Err(_) => return Ok(()),
+    };
+    match trusted {
+        Some(trusted) if revision < trusted => Err(anyhow!(
+            "Vault rollback detected (loaded revision {revision} is older than trusted revision {trusted}). \
+             If you restored this vault on purpose, run --reset-revision {trusted}"
+        )),
+        Some(trusted) if revision == trusted => Ok(()),
+        _ => {
+            if let Err(e) = store.store_trusted_revision(revision) {
+                note_trusted_revision_failure(&e);
+            }
+            Ok(())
+        }
+    }
 }
 
 fn validate_configured_vault_dir(raw: &Path) -> Result<PathBuf> {
@@ -359,21 +1892,79 @@ fn validate_configured_vault_dir(raw: &Path) -> Result<PathBuf> {
                 home.display()
             ));
         }
-    } else if let Some(parent) = candidate.parent() {
-        if parent.exists() {
-            let real_parent = fs::canonicalize(parent)?;
-            if !real_parent.starts_with(&home_real) {
-                return Err(anyhow!(
-                    "Configured vault parent resolves outside home directory ({})",
-                    home.display()
-                ));
-            }
+    } else if let Some(parent) = candidate.parent()
+        && parent.exists()
+    {
+        let real_parent = fs::canonicalize(parent)?;
+        if !real_parent.starts_with(&home_real) {
+            return Err(anyhow!(
+                "Configured vault parent resolves outside home directory ({})",
+                home.display()
+            ));
         }
     }
 
     Ok(candidate)
 }
 
+/// Overwrite a file with zeros before unlinking it. Best-effort only: journaling
+/// filesystems and SSD wear-leveling may still keep older copies of the blocks.
+pub fn secure_remove_file(path: &Path) -> Result<()> {
+    let len = fs::metadata(path)?.len() as usize;
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    file.write_all(&vec![0u8; len])?;
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Headroom beyond the vault itself: the audit log append and filesystem
+/// metadata need room too.
+const FREE_SPACE_MARGIN_BYTES: u64 = 1024 * 1024;
+
+/// Refuses a save that might not fit, rather than letting the temp file run the
+/// disk dry halfway through. Best-effort: if free space can't be queried, the
+/// save goes ahead.
+fn ensure_free_space(path: &Path, needed: u64) -> Result<()> {
+    let Some(dir) = path.parent() else {
+        return Ok(());
+    };
+    if let Some(available) = available_space(dir) {
+        let required = needed + FREE_SPACE_MARGIN_BYTES;
+        if available < required {
+            return Err(anyhow!(
+                "Not saving: only {} KiB free in {}, need at least {} KiB. Free some space and try again; the vault on disk is unchanged",
+                available / 1024,
+                dir.display(),
+                required.div_ceil(1024)
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn available_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is NUL-terminated and `stats` is only read after statvfs
+    // reports success, at which point it has been filled in.
+    let stats = unsafe {
+        if libc::statvfs(c_path.as_ptr(), stats.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stats.assume_init()
+    };
+    Some((stats.f_bavail as u64).saturating_mul(stats.f_frsize as u64))
+}
+
+#[cfg(not(unix))]
+fn available_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
 fn atomic_write(path: &Path, bytes: &[u8]) -> Result<()> {
     let parent = path.parent().ok_or_else(|| anyhow!("Invalid target path"))?;
     if !parent.exists() {
@@ -390,13 +1981,153 @@ fn atomic_write(path: &Path, bytes: &[u8]) -> Result<()> {
     Ok(())
 }
 
+/// No policy may ask for less than this.
+const MIN_PASSPHRASE_LENGTH: usize = 8;
+const MAX_PASSPHRASE_LENGTH: usize = 1024;
+
+/// A kind of character a master passphrase can be required to contain.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum CharClass {
+    Upper,
+    Lower,
+    Digit,
+    Special,
+}
+
+impl CharClass {
+    pub fn matches(self, c: char) -> bool {
+        match self {
+            CharClass::Upper => c.is_ascii_uppercase(),
+            CharClass::Lower => c.is_ascii_lowercase(),
+            CharClass::Digit => c.is_ascii_digit(),
+            CharClass::Special => !c.is_alphanumeric() && !c.is_whitespace(),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CharClass::Upper => "uppercase letter",
+            CharClass::Lower => "lowercase letter",
+            CharClass::Digit => "number",
+            CharClass::Special => "special character",
+        }
+    }
+}
+
+/// What a new master or recovery passphrase must satisfy. The default is the
+/// long-standing 8+ characters with an uppercase letter, a number and a
+/// special character; deployments can raise it in `config.json`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PassphrasePolicy {
+    pub min_length: usize,
+    pub require: Vec<CharClass>,
+    /// Strength meter level (1 Weak to 4 Excellent) below which a passphrase
+    /// is refused. Unset only asks for confirmation below Strong.
+    pub min_strength: Option<u8>,
+}
+
+impl Default for PassphrasePolicy {
+    fn default() -> Self {
+        Self {
+            min_length: MIN_PASSPHRASE_LENGTH,
+            require: vec![CharClass::Upper, CharClass::Digit, CharClass::Special],
+            min_strength: None,
+        }
+    }
+}
+
+impl PassphrasePolicy {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Checked when the config is read, so a broken policy is reported up
+    /// front rather than at the next passphrase change.
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_PASSPHRASE_LENGTH..=MAX_PASSPHRASE_LENGTH).contains(&self.min_length) {
+            return Err(anyhow!(
+                "master_policy.min_length must be between {MIN_PASSPHRASE_LENGTH} and {MAX_PASSPHRASE_LENGTH}, got {}",
+                self.min_length
+            ));
+        }
+        if let Some((idx, class)) = self.require.iter().enumerate().find(|(idx, c)| self.require[..*idx].contains(c)) {
+            return Err(anyhow!(
+                "master_policy.require lists {class:?} twice (item {})",
+                idx + 1
+            ));
+        }
+        if let Some(level) = self.min_strength
+            && !(1..=4).contains(&level)
+        {
+            return Err(anyhow!("master_policy.min_strength must be 1-4, got {level}"));
+        }
+        Ok(())
+    }
+
+    /// `8+, uppercase letter, number, special character` for form hints.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{}+", self.min_length)];
+        parts.extend(self.require.iter().map(|class| class.label().to_string()));
+        if let Some(level) = self.min_strength {
+            parts.push(format!("strength level {level}+"));
+        }
+        parts.join(", ")
+    }
+}
+
+/// The configured [`PassphrasePolicy`], read once per process.
+pub fn passphrase_policy() -> Result<PassphrasePolicy> {
+    static RESOLVED: OnceLock<Result<PassphrasePolicy, String>> = OnceLock::new();
+    RESOLVED
+        .get_or_init(|| {
+            load_config()
+                .map(|cfg| cfg.map(|cfg| cfg.master_policy).unwrap_or_default())
+                .map_err(|e| e.to_string())
+        })
+        .clone()
+        .map_err(|e| anyhow!(e))
+}
+
+const STRICT_FILE_MODE: u32 = 0o600;
+const STRICT_DIR_MODE: u32 = 0o700;
+const MAX_FILE_MODE: u32 = 0o640;
+const MAX_DIR_MODE: u32 = 0o750;
+
+/// Modes applied to created files and the vault directory.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PermissionModes {
+    pub file: u32,
+    pub dir: u32,
+}
+
+/// The configured [`PermissionModes`], resolved once per process. No config
+/// means the strict defaults; an invalid entry is an error every time.
+pub fn permission_modes() -> Result<PermissionModes> {
+    static RESOLVED: OnceLock<Result<PermissionModes, String>> = OnceLock::new();
+    RESOLVED
+        .get_or_init(|| {
+            load_config()
+                .map_err(|e| e.to_string())?
+                .unwrap_or_default()
+                .permission_modes()
+                .map_err(|e| e.to_string())
+        })
+        .clone()
+        .map_err(|e| anyhow!(e))
+}
+
 fn restrict_file(path: &Path) -> Result<()> {
+    set_file_mode(path, permission_modes()?.file)
+}
+
+fn set_file_mode(path: &Path, mode: u32) -> Result<()> {
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
         if path.exists() {
-            let perms = fs::Permissions::from_mode(0o600);
-            fs::set_permissions(path, perms)?;
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
         }
     }
     // On non-Unix platforms we skip explicit chmod; rely on platform defaults.
@@ -408,9 +2139,345 @@ fn restrict_dir(path: &Path) -> Result<()> {
     {
         use std::os::unix::fs::PermissionsExt;
         if path.exists() {
-            let perms = fs::Permissions::from_mode(0o700);
+            let perms = fs::Permissions::from_mode(permission_modes()?.dir);
             fs::set_permissions(path, perms)?;
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trusted revision held in memory instead of the keyring.
+    struct MemoryStore(Mutex<Option<u64>>);
+
+    impl SecretStore for MemoryStore {
+        fn load_trusted_revision(&self) -> Result<Option<u64>> {
+            Ok(*self.0.lock().unwrap())
+        }
+
+        fn store_trusted_revision(&self, revision: u64) -> Result<()> {
+            *self.0.lock().unwrap() = Some(revision);
+            Ok(())
+        }
+
+        fn load_wrapped_key(&self) -> Result<Option<[u8; 32]>> {
+            Ok(None)
+        }
+
+        fn clear_wrapped_key(&self) -> Result<bool> {
+            Ok(false)
+        }
+    }
+
+    fn trusted(store: &MemoryStore) -> Option<u64> {
+        *store.0.lock().unwrap()
+    }
+
+    /// Cheap KDF, no keyring and the strict modes, whatever the machine's
+    /// `config.json` and keyring say.
+    fn test_env() -> VaultEnv {
+        VaultEnv {
+            kdf: KdfParams { m_cost: 8, t_cost: 1, p_cost: 1, algorithm: KdfAlgorithm::Argon2id },
+            backend: KeyringBackend::None,
+            modes: PermissionModes { file: STRICT_FILE_MODE, dir: STRICT_DIR_MODE },
+        }
+    }
+
+    #[test]
+    fn older_revision_is_a_rollback() {
+        let store = MemoryStore(Mutex::new(Some(7)));
+        let err = check_revision(&store, 5).unwrap_err();
+        assert!(err.to_string().contains("rollback"));
+        assert_eq!(trusted(&store), Some(7));
+    }
+
+    #[test]
+    fn newer_revision_moves_trusted_up() {
+        let store = MemoryStore(Mutex::new(Some(7)));
+        check_revision(&store, 9).unwrap();
+        assert_eq!(trusted(&store), Some(9));
+    }
+
+    #[test]
+    fn equal_revision_passes() {
+        let store = MemoryStore(Mutex::new(Some(7)));
+        check_revision(&store, 7).unwrap();
+        assert_eq!(trusted(&store), Some(7));
+    }
+
+    #[test]
+    fn missing_trusted_revision_is_seeded() {
+        let store = MemoryStore(Mutex::new(None));
+        check_revision(&store, 3).unwrap();
+        assert_eq!(trusted(&store), Some(3));
+    }
+
+    #[test]
+    fn lock_counts_down_and_expires() {
+        let lock = LockState { unlock_at: 1_120, locked_at: Some(1_000), failed_attempts: 0 };
+        assert_eq!(lock.remaining(1_000), Some(120));
+        assert_eq!(lock.remaining(1_100), Some(20));
+        assert_eq!(lock.remaining(1_120), None);
+        assert!(!lock.rolled_back(1_100));
+    }
+
+    #[test]
+    fn clock_rollback_keeps_lock_active() {
+        let lock = LockState { unlock_at: 10_120, locked_at: Some(10_000), failed_attempts: 0 };
+        // Clock turned back an hour after the lock was set.
+        assert!(lock.rolled_back(10_000 - 3_600));
+        assert_eq!(lock.remaining(10_000 - 3_600), Some(120));
+    }
+
+    fn vault_from(json: &str) -> Vault {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn export_order_ignores_storage_order() {
+        let a = vault_from(
+            r#"{"revision": 3,
+                "entries": [
+                    {"id": "2", "name": "mail", "email": "b@x", "password": "p2"},
+                    {"id": "1", "name": "bank", "email": "a@x", "password": "p1"},
+                    {"id": "3", "name": "mail", "email": "a@x", "password": "p3"}
+                ],
+                "notes": [
+                    {"id": "n2", "title": "wifi", "content": "c2"},
+                    {"id": "n1", "title": "alarm", "content": "c1"}
+                ]}"#,
+        );
+        let mut b = vault_from(&serde_json::to_string(&a).unwrap());
+        b.entries.reverse();
+        b.notes.reverse();
+        b.revision = 4;
+
+        let first = export_vault_json(&a).unwrap();
+        assert_eq!(first, export_vault_json(&a).unwrap());
+        assert_eq!(first, export_vault_json(&b).unwrap());
+
+        let exported: serde_json::Value = serde_json::from_str(&first).unwrap();
+        let ids: Vec<&str> = exported["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, ["1", "3", "2"]);
+        assert_eq!(exported["notes"][0]["title"], "alarm");
+    }
+
+    #[test]
+    fn compressed_vault_round_trips_through_encryption() {
+        let entries: Vec<String> = (0..2_000)
+            .map(|i| {
+                format!(
+                    r#"{{"id": "{i}", "name": "service-{}", "email": "user{i}@example.com",
+                        "password": "pw-{i}-Xy9!", "notes": "recovery codes live in the notes pane"}}"#,
+                    i % 50
+                )
+            })
+            .collect();
+        let mut vault = vault_from(&format!(r#"{{"revision": 12, "entries": [{}]}}"#, entries.join(",")));
+        vault.notes.push(Note {
+            id: "n1".into(),
+            title: "codes".into(),
+            content: "0000-1111-2222\n".repeat(5_000).into(),
+        });
+        let key = [7u8; 32];
+        let plain = encode_vault(&vault, None).unwrap();
+        let packed = encode_vault(&vault, Some(VaultCompression::Zlib)).unwrap();
+        assert!(packed.len() * 4 < plain.len());
+
+        let enc = encrypt_with_key(&key, &packed).unwrap();
+        let back = decode_vault(&decrypt_with_key(&key, &enc).unwrap(), Some(VaultCompression::Zlib)).unwrap();
+        assert_eq!(export_vault_json(&back).unwrap(), export_vault_json(&vault).unwrap());
+        assert_eq!(back.revision, 12);
+        // Files without the flag are plain JSON, as before the option.
+        assert_eq!(decode_vault(&plain, None).unwrap().entries.len(), 2_000);
+        assert!(decode_vault(&plain, Some(VaultCompression::Zlib)).is_err());
+    }
+
+    #[test]
+    fn permission_modes_allow_group_read_at_most() {
+        let with = |file: &str, dir: &str| Config {
+            file_mode: Some(file.to_string()),
+            dir_mode: Some(dir.to_string()),
+            ..Config::default()
+        };
+        let modes = with("0640", "0750").permission_modes().unwrap();
+        assert_eq!(modes, PermissionModes { file: 0o640, dir: 0o750 });
+        assert_eq!(Config::default().permission_modes().unwrap().file, 0o600);
+        assert!(with("0660", "0700").permission_modes().is_err());
+        assert!(with("0644", "0700").permission_modes().is_err());
+        assert!(with("0400", "0700").permission_modes().is_err());
+        assert!(with("0600", "0755").permission_modes().is_err());
+        assert!(with("rw-r-----", "0700").permission_modes().is_err());
+    }
+
+    #[test]
+    fn version_0_config_is_upgraded() {
+        let mut raw: serde_json::Map<String, serde_json::Value> = serde_json::from_str(
+            r#"{"mask_char": "*", "kdf_m_cost": null, "clipboard_clear_secs": null}"#,
+        )
+        .unwrap();
+        let base = tempfile::tempdir().unwrap();
+        migrate_config(&mut raw, 0, base.path()).unwrap();
+        let cfg: Config = serde_json::from_value(serde_json::Value::Object(raw)).unwrap();
+        assert_eq!(cfg.config_version, CONFIG_VERSION);
+        assert_eq!(cfg.vault_dir, base.path().to_str().unwrap());
+        assert_eq!(cfg.mask_char, '*');
+        assert_eq!(cfg.settings.clipboard_clear_secs, default_clipboard_clear_secs());
+    }
+
+    #[test]
+    fn reading_an_old_config_leaves_the_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        let original = r#"{"vault_dir": "v", "mask_char": null}"#;
+        fs::write(&path, original).unwrap();
+        let cfg = read_config_at(&path).unwrap().unwrap();
+        assert_eq!(cfg.config_version, CONFIG_VERSION);
+        assert_eq!(cfg.mask_char, default_mask_char());
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+    }
+
+    #[test]
+    fn kdf_algorithm_must_be_a_known_variant() {
+        let cfg: Config = serde_json::from_str(r#"{"vault_dir": "v", "kdf_algorithm": "argon2i"}"#).unwrap();
+        assert_eq!(cfg.kdf_algorithm, KdfAlgorithm::Argon2i);
+        assert!(serde_json::from_str::<Config>(r#"{"vault_dir": "v", "kdf_algorithm": "argon2x"}"#).is_err());
+        let spec: KdfSpec = serde_json::from_str(r#"{"m_cost": 8, "t_cost": 1, "p_cost": 1}"#).unwrap();
+        assert_eq!(spec.algorithm, KdfAlgorithm::Argon2id);
+        assert!(serde_json::from_str::<KdfSpec>(r#"{"m_cost": 8, "t_cost": 1, "p_cost": 1, "algorithm": "scrypt"}"#).is_err());
+    }
+
+    #[test]
+    fn vault_settings_never_reach_config_json() {
+        let mut cfg: Config = serde_json::from_str(
+            r#"{"vault_dir": "v", "mask_char": "*", "last_service": "github", "special_chars": "!?"}"#,
+        )
+        .unwrap();
+        assert_eq!(cfg.settings.last_service.as_deref(), Some("github"));
+        assert_eq!(cfg.settings.special_chars, "!?");
+        cfg.settings.service_sort = ServiceSort::Recent;
+        let written = serde_json::to_value(&cfg).unwrap();
+        let written = written.as_object().unwrap();
+        assert_eq!(written["mask_char"], "*");
+        assert!(written.keys().all(|k| !is_vault_setting_key(k)), "{written:?}");
+        for key in ["last_service", "service_sort", "default_gen_length", "vault_compression", "clipboard_enabled"] {
+            assert!(is_vault_setting_key(key), "{key}");
+        }
+        assert!(!is_vault_setting_key("mask_char"));
+        assert_eq!(VaultSettings::default().clipboard_clear_secs, default_clipboard_clear_secs());
+    }
+
+    #[test]
+    fn one_shot_failures_are_counted_until_a_success() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = dir.path().join(LOCK_FILE);
+        assert_eq!(record_failed_attempt_in(&lock, 3, 120, STRICT_FILE_MODE).unwrap(), 2);
+        ensure_lock_not_active(&lock).unwrap();
+        assert_eq!(record_failed_attempt_in(&lock, 3, 120, STRICT_FILE_MODE).unwrap(), 1);
+        clear_failed_attempts(&lock).unwrap();
+        assert!(!lock.exists());
+        assert_eq!(record_failed_attempt_in(&lock, 3, 120, STRICT_FILE_MODE).unwrap(), 2);
+    }
+
+    #[test]
+    fn legacy_lock_without_creation_time_still_loads() {
+        let lock: LockState = serde_json::from_str(r#"{"unlock_at": 1120}"#).unwrap();
+        assert_eq!(lock.remaining(1_000), Some(120));
+        assert_eq!(lock.remaining(2_000), None);
+    }
+
+    #[test]
+    fn passphrase_policy_must_be_self_consistent() {
+        assert!(PassphrasePolicy::default().validate().is_ok());
+        let policy: PassphrasePolicy =
+            serde_json::from_str(r#"{"min_length": 14, "require": ["lower", "digit"], "min_strength": 3}"#).unwrap();
+        assert!(policy.validate().is_ok());
+        assert_eq!(policy.summary(), "14+, lowercase letter, number, strength level 3+");
+        let invalid = |raw: &str| serde_json::from_str::<PassphrasePolicy>(raw).unwrap().validate().is_err();
+        assert!(invalid(r#"{"min_length": 6}"#));
+        assert!(invalid(r#"{"require": ["upper", "digit", "upper"]}"#));
+        assert!(invalid(r#"{"min_strength": 5}"#));
+        assert!(serde_json::from_str::<PassphrasePolicy>(r#"{"require": ["emoji"]}"#).is_err());
+    }
+
+    #[test]
+    fn only_a_rejected_passphrase_counts_as_wrong() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(VAULT_FILE);
+        let env = test_env();
+        save_vault_logged_in(&path, &Vault::default(), "Correct-Horse-9", &[], &env).unwrap();
+        let Err(wrong) = load_vault_in(&path, "Battery-Staple-9", &env) else { panic!("opened with a wrong passphrase") };
+        assert!(is_wrong_passphrase(&wrong), "{wrong}");
+        fs::write(&path, "{ not json").unwrap();
+        let Err(corrupt) = load_vault_in(&path, "Correct-Horse-9", &env) else { panic!("opened a corrupt file") };
+        assert!(!is_wrong_passphrase(&corrupt), "{corrupt}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lock_dir_must_be_private_to_this_user() {
+        use std::os::unix::fs::PermissionsExt;
+        let base = tempfile::tempdir().unwrap();
+        let dir = private_lock_dir(base.path()).unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        assert_eq!(private_lock_dir(base.path()).unwrap(), dir);
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(private_lock_dir(base.path()).is_err());
+
+        fs::remove_dir(&dir).unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(elsewhere.path(), &dir).unwrap();
+        assert!(private_lock_dir(base.path()).is_err());
+    }
+
+    #[test]
+    fn logged_events_leave_the_vault_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(VAULT_FILE);
+        let env = test_env();
+        save_vault_logged_in(&path, &Vault::default(), "Correct-Horse-9", &[], &env).unwrap();
+        let before = fs::read(&path).unwrap();
+        log_audit_events_in(&path, "Correct-Horse-9", &[AuditEvent::new("export", "out.json")], &env).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), before);
+        let events = read_audit_log(&path, "Correct-Horse-9").unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].kind.as_str(), events[0].detail.as_str()), ("export", "out.json"));
+    }
+
+    #[test]
+    fn interrupted_rotation_keeps_the_log_that_matches_the_vault() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(VAULT_FILE);
+        let pass = "Correct-Horse-9";
+        let env = test_env();
+        save_vault_logged_in(&path, &Vault::default(), pass, &[], &env).unwrap();
+        log_audit_events_in(&path, pass, &[AuditEvent::new("export", "out.json")], &env).unwrap();
+        let log = audit_log_path(&path);
+        let pending = pending_audit_log_path(&path);
+        let foreign = format!("{}\n", serde_json::to_string(&encrypt_with_key(&[7u8; 32], b"{}").unwrap()).unwrap());
+
+        // Stopped before the vault was rewritten: the staged log is dropped.
+        fs::write(&pending, &foreign).unwrap();
+        assert_eq!(read_audit_log(&path, pass).unwrap().len(), 1);
+        assert!(!pending.exists());
+
+        // Stopped after: the staged log opens under the vault's key and wins.
+        fs::copy(&log, &pending).unwrap();
+        fs::write(&log, &foreign).unwrap();
+        assert_eq!(read_audit_log(&path, pass).unwrap().len(), 1);
+        assert!(!pending.exists());
+
+        rotate_dek_in(&path, &Vault::default(), pass, None, &[AuditEvent::new("rotate-dek", "revision 0")], &env).unwrap();
+        assert_eq!(read_audit_log(&path, pass).unwrap().len(), 2);
+        assert!(!pending.exists());
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+pub const TOTP_PERIOD_SECS: u64 = 30;
+const TOTP_DIGITS: u32 = 6;
+
+/// RFC 6238 code (HMAC-SHA1, 30s step, 6 digits) for the given base32 secret,
+/// plus the seconds left before it rolls over.
+pub fn current_code(secret: &str) -> Result<(String, u64)> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| anyhow!("Clock error: {e}"))?
+        .as_secs();
+    let key = decode_secret(secret)?;
+    let remaining = TOTP_PERIOD_SECS - (now % TOTP_PERIOD_SECS);
+    Ok((code_at(&key, now), remaining))
+}
+
+pub fn code_at(key: &[u8], unix_time: u64) -> String {
+    let counter = unix_time / TOTP_PERIOD_SECS;
+    let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let truncated = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        truncated % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+/// Decodes an authenticator secret (RFC 4648 base32). Case, spaces, dashes and
+/// trailing padding are tolerated since apps display secrets in varied forms.
+pub fn decode_secret(secret: &str) -> Result<Vec<u8>> {
+    let mut bits: u64 = 0;
+    let mut bit_count = 0u32;
+    let mut out = Vec::new();
+    for c in secret.chars().filter(|c| !matches!(c, ' ' | '-' | '=')) {
+        let value = match c.to_ascii_uppercase() {
+            ch @ 'A'..='Z' => ch as u64 - 'A' as u64,
+            ch @ '2'..='7' => ch as u64 - '2' as u64 + 26,
+            _ => return Err(anyhow!("TOTP secret is not valid base32")),
+        };
+        bits = (bits << 5) | value;
+        bit_count += 5;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            out.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+    if out.is_empty() {
+        return Err(anyhow!("TOTP secret is empty"));
+    }
+    Ok(out)
+}
+
+/// `otpauth://` enrolment URI (Key Uri Format) for authenticator apps.
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    let secret: String = secret
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '='))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    format!(
+        "otpauth://totp/{}:{}?secret={secret}&issuer={}&period={TOTP_PERIOD_SECS}&digits={TOTP_DIGITS}",
+        percent_encode(issuer),
+        percent_encode(account),
+        percent_encode(issuer)
+    )
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut out = String::new();
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The RFC 6238 SHA-1 seed, "12345678901234567890", in base32.
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn matches_rfc_6238_sha1_vectors() {
+        let key = decode_secret(RFC_SECRET).unwrap();
+        assert_eq!(key, b"12345678901234567890");
+        // The RFC lists 8 digits (94287082, 07081804); six-digit codes are
+        // the last six of the same truncated value.
+        assert_eq!(code_at(&key, 59), "287082");
+        assert_eq!(code_at(&key, 1_111_111_109), "081804");
+        assert_eq!(code_at(&key, 1_111_111_111), "050471");
+        assert_eq!(code_at(&key, 1_234_567_890), "005924");
+    }
+
+    #[test]
+    fn padding_case_and_separators_are_tolerated() {
+        assert_eq!(decode_secret("MZXW6===").unwrap(), b"foo");
+        assert_eq!(decode_secret("MZXW6YQ=").unwrap(), b"foob");
+        assert_eq!(decode_secret("mzxw6yq").unwrap(), b"foob");
+        assert_eq!(decode_secret("gezd gnbv-gy3t qojq").unwrap(), &b"1234567890"[..]);
+    }
+
+    #[test]
+    fn invalid_secrets_are_rejected() {
+        assert!(decode_secret("").is_err());
+        assert!(decode_secret("====").is_err());
+        assert!(decode_secret("MZXW1").is_err());
+        assert!(decode_secret("MZXW8").is_err());
+        assert!(decode_secret("MZ!W6").is_err());
+    }
+
+    #[test]
+    fn otpauth_uri_escapes_label_and_issuer() {
+        assert_eq!(
+            otpauth_uri("Acme Co", "alice@example.com", "jbsw y3dp-ehpk3pxp=="),
+            "otpauth://totp/Acme%20Co:alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Acme%20Co&period=30&digits=6"
+        );
+        let uri = otpauth_uri("a:b&c", "x?y", "MZXW6");
+        assert!(uri.starts_with("otpauth://totp/a%3Ab%26c:x%3Fy?"));
+        assert!(uri.contains("&issuer=a%3Ab%26c&"));
+    }
+}
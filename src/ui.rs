@@ -1,22 +1,28 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
 use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex, OnceLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
 use arboard::Clipboard;
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use base64::Engine;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled};
 use ratatui::{
     prelude::*,
     style::Style,
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{block::Title, Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 // big text banner is rendered via Paragraph using block characters
 use rpassword::prompt_password;
+use zeroize::Zeroize;
 
-use crate::models::{Entry, Note, Vault};
+use crate::models::{unix_now, Card, CustomField, Entry, EntryType, Note, SecretString, SshKey, Vault};
 
-const CLIPBOARD_LIFETIME_SECS: u64 = 20;
 const COLOR_SAND: Color = Color::Rgb(0xEB, 0xDB, 0xB2);
 const COLOR_OLIVE: Color = Color::Rgb(0x98, 0x97, 0x1A); // kept for future accents
 const COLOR_MOSS: Color = Color::Rgb(0x67, 0x67, 0x1C);
@@ -77,7 +83,7 @@ fn themed_overlay(title: &str) -> OverlayTheme {
 }
 
 fn centered_overlay_area(frame_size: Rect, lines: &[String]) -> Rect {
-    let maxw = lines.iter().map(|s| s.len()).max().unwrap_or(0) as u16 + 4;
+    let maxw = lines.iter().map(|s| s.chars().count()).max().unwrap_or(0) as u16 + 4;
     let maxh = lines.len() as u16 + 2;
     Rect::new(
         (frame_size.width.saturating_sub(maxw)) / 2,
@@ -175,6 +181,8 @@ pub struct ViewState<'a> {
     pub service_idx: usize,
     pub entry_idx: usize,
     pub focus_services: bool,
+    /// Compact layout only: the credentials focus shows the detail pane instead.
+    pub show_detail: bool,
     pub delete_overlay: Option<String>,
     pub overlay: Option<Vec<String>>,
     pub overlay_title: Option<String>,
@@ -182,6 +190,18 @@ pub struct ViewState<'a> {
     pub status: String,
     pub status_strength: Option<StatusStrength>,
     pub detail_strength_override: Option<StatusStrength>,
+    pub password_age_warn_days: u64,
+    pub mask_char: char,
+    /// Append the clipboard auto-clear countdown to the status line.
+    pub clipboard_countdown: bool,
+    pub entry_label: &'a EntryLabel,
+    /// Add an `ID:` line with the selected credential's `id`.
+    pub show_entry_id: bool,
+    /// Lines the detail pane is scrolled down by (`PgUp`/`PgDn`).
+    pub detail_scroll: u16,
+    pub strength_cache: &'a StrengthCache,
+    /// Query typed at the `/` find prompt; its matches are highlighted.
+    pub search: Option<&'a str>,
 }
 
 pub struct UnlockState<'a> {
@@ -189,15 +209,18 @@ pub struct UnlockState<'a> {
     pub input_display: &'a str,
     pub input_visible: bool,
     pub anim_frame: usize,
+    pub minimal: bool,
 }
 
 pub struct NoteViewState<'a> {
     pub vault: &'a Vault,
     pub note_idx: usize,
+    pub content_scroll: u16,
     pub delete_overlay: Option<String>,
     pub add_overlay: Option<Vec<String>>,
     pub status: String,
     pub quit_overlay: Option<Vec<String>>,
+    pub list_percent: u16,
 }
 
 #[derive(Clone)]
@@ -206,6 +229,36 @@ pub struct StatusStrength {
     pub level: u8,
 }
 
+/// Strength of each login by entry id, rescored only when the password's
+/// hash changes, so redraws don't classify every password again. Lives in the
+/// TUI loop, not the vault; the hash is keyed per session.
+#[derive(Default)]
+pub struct StrengthCache {
+    hasher: RandomState,
+    scores: RefCell<HashMap<String, (u64, StatusStrength)>>,
+}
+
+impl StrengthCache {
+    pub fn get(&self, entry: &Entry) -> StatusStrength {
+        let hash = self.hasher.hash_one(entry.password.expose());
+        let mut scores = self.scores.borrow_mut();
+        if let Some((cached, strength)) = scores.get(&entry.id)
+            && *cached == hash
+        {
+            return strength.clone();
+        }
+        let strength = classify_password_strength(entry.password.expose());
+        scores.insert(entry.id.clone(), (hash, strength.clone()));
+        strength
+    }
+}
+
+/// Right-aligned `rev N` on the footer border, to compare against
+/// `--reset-revision` and rollback messages.
+fn revision_title(vault: &Vault) -> Title<'static> {
+    Title::from(format!(" rev {} ", vault.revision)).alignment(Alignment::Right)
+}
+
 pub fn classify_password_strength(password: &str) -> StatusStrength {
     let len = password.chars().count();
     if len < 8 {
@@ -261,6 +314,81 @@ pub fn classify_password_strength(password: &str) -> StatusStrength {
     }
 }
 
+/// `Label: value`, with a value that is one long token (a URL, a public key)
+/// moved to its own indented line when it doesn't fit beside the label, so it
+/// wraps by itself instead of after the label. Multi-line values keep their
+/// lines.
+fn value_lines(label: &str, value: &str, width: usize) -> Vec<Line<'static>> {
+    let inline = format!("{label}: {value}");
+    let single_token = !value.is_empty() && !value.chars().any(char::is_whitespace);
+    if value.contains('\n') || (single_token && inline.chars().count() > width) {
+        let mut lines = vec![Line::from(format!("{label}:"))];
+        lines.extend(value.lines().flat_map(|line| indented_lines(line, width)));
+        lines
+    } else {
+        vec![Line::from(inline)]
+    }
+}
+
+/// Notes inline when they fit on one line, otherwise under a `Notes:`
+/// heading with every line kept (blank lines and leading spaces included).
+fn notes_lines(notes: Option<&str>, width: usize) -> Vec<Line<'static>> {
+    let notes = notes.filter(|n| !n.trim().is_empty()).unwrap_or("-");
+    let inline = format!("Notes: {notes}");
+    if !notes.contains('\n') && inline.chars().count() <= width {
+        return vec![Line::from(inline)];
+    }
+    let mut lines = vec![Line::from("Notes:")];
+    lines.extend(notes.lines().flat_map(|line| indented_lines(line, width)));
+    lines
+}
+
+/// One note line indented by two spaces. A line too wide for the pane is
+/// split around its URLs so each URL starts its own line and wraps alone.
+fn indented_lines(line: &str, width: usize) -> Vec<Line<'static>> {
+    if line.chars().count() + 2 <= width || !line.contains("://") {
+        return vec![Line::from(format!("  {line}"))];
+    }
+    let mut lines = Vec::new();
+    let mut text = String::new();
+    for word in line.split(' ') {
+        if word.contains("://") {
+            if !text.trim().is_empty() {
+                lines.push(Line::from(format!("  {}", text.trim_end())));
+            }
+            text.clear();
+            lines.push(Line::from(format!("  {word}")));
+        } else {
+            text.push_str(word);
+            text.push(' ');
+        }
+    }
+    if !text.trim().is_empty() {
+        lines.push(Line::from(format!("  {}", text.trim_end())));
+    }
+    lines
+}
+
+/// `  clipboard [====----] 12s`, drawn like the strength meter: the bar
+/// shrinks as the auto-clear nears and turns from green to red.
+fn countdown_spans(remaining: Duration, total: Duration) -> Vec<Span<'static>> {
+    let width = 12usize;
+    let fraction = remaining.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON);
+    let filled = ((fraction * width as f64).ceil() as usize).min(width);
+    let level = match fraction {
+        f if f > 0.5 => 3,
+        f if f > 0.2 => 2,
+        _ => 1,
+    };
+    let color = strength_color(level);
+    vec![
+        Span::raw("  clipboard ["),
+        Span::styled("=".repeat(filled), Style::default().fg(color)),
+        Span::styled("-".repeat(width - filled), Style::default().fg(Color::DarkGray)),
+        Span::raw(format!("] {}s", remaining.as_secs_f64().ceil() as u64)),
+    ]
+}
+
 fn strength_color(level: u8) -> Color {
     match level.clamp(1, 4) {
         1 => Color::Red,
@@ -270,20 +398,235 @@ fn strength_color(level: u8) -> Color {
     }
 }
 
-pub fn draw(f: &mut Frame<'_>, state: &ViewState) {
+#[derive(Clone, Debug, PartialEq)]
+enum LabelPart {
+    Text(String),
+    Name,
+    /// The username, else the email.
+    User,
+    Username,
+    Email,
+    Url,
+}
+
+/// An `entry_label` template for the credentials list, parsed once per
+/// session. Placeholders are `{name}`, `{user}`, `{username}`, `{email}`
+/// and `{url}`; `{{` and `}}` are literal braces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntryLabel(Vec<LabelPart>);
+
+impl Default for EntryLabel {
+    /// `{user} ({email})`, the label from before it was configurable.
+    fn default() -> Self {
+        Self(vec![
+            LabelPart::User,
+            LabelPart::Text(" (".into()),
+            LabelPart::Email,
+            LabelPart::Text(")".into()),
+        ])
+    }
+}
+
+impl EntryLabel {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => text.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => text.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(anyhow!("entry_label has an unclosed '{{'")),
+                        }
+                    }
+                    let part = match name.as_str() {
+                        "name" => LabelPart::Name,
+                        "user" => LabelPart::User,
+                        "username" => LabelPart::Username,
+                        "email" => LabelPart::Email,
+                        "url" => LabelPart::Url,
+                        _ => {
+                            return Err(anyhow!(
+                                "entry_label has unknown placeholder {{{name}}}; use {{name}}, {{user}}, {{username}}, {{email}} or {{url}}"
+                            ));
+                        }
+                    };
+                    if !text.is_empty() {
+                        parts.push(LabelPart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(part);
+                }
+                '}' => return Err(anyhow!("entry_label has a '}}' without a '{{'; write '}}}}' for a literal one")),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(LabelPart::Text(text));
+        }
+        Ok(Self(parts))
+    }
+
+    /// Empty values show as `-` so the label keeps its shape.
+    pub fn render(&self, entry: &Entry) -> String {
+        let or_dash = |v: &str| if v.trim().is_empty() { "-".to_string() } else { v.to_string() };
+        self.0
+            .iter()
+            .map(|part| match part {
+                LabelPart::Text(text) => text.clone(),
+                LabelPart::Name => or_dash(&entry.name),
+                LabelPart::User => {
+                    or_dash(entry.username.as_deref().filter(|u| !u.trim().is_empty()).unwrap_or(&entry.email))
+                }
+                LabelPart::Username => or_dash(entry.username.as_deref().unwrap_or_default()),
+                LabelPart::Email => or_dash(&entry.email),
+                LabelPart::Url => or_dash(entry_url(entry).unwrap_or_default()),
+            })
+            .collect()
+    }
+}
+
+/// Entries have no URL of their own; it is the first plain custom field
+/// labelled like one ("URL", "Login URL", "Website").
+fn entry_url(entry: &Entry) -> Option<&str> {
+    entry
+        .fields
+        .iter()
+        .find(|field| {
+            let label = field.label.trim().to_ascii_lowercase();
+            !field.secret && (label.split_whitespace().any(|word| word == "url") || label == "website")
+        })
+        .map(|field| field.value.expose())
+}
+
+/// Byte ranges of `query` in `text`, ignoring ASCII case, left to right and
+/// non-overlapping. An empty query matches nothing.
+pub fn match_ranges(text: &str, query: &str) -> Vec<(usize, usize)> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    // ASCII lowercasing keeps byte offsets, so ranges index `text` directly.
+    let query = query.to_ascii_lowercase();
+    text.to_ascii_lowercase()
+        .match_indices(&query)
+        .map(|(at, m)| (at, at + m.len()))
+        .collect()
+}
+
+/// Whether the find prompt's query matches a credential: its service,
+/// username, email or notes.
+pub fn entry_matches(entry: &Entry, query: &str) -> bool {
+    hidden_match_field(entry, query).is_some() || !match_ranges(&entry.name, query).is_empty()
+}
+
+/// First of username, email and notes the query matches, for rows whose
+/// label doesn't show the match.
+fn hidden_match_field(entry: &Entry, query: &str) -> Option<&'static str> {
+    [
+        ("username", entry.username.as_deref()),
+        ("email", Some(entry.email.as_str())),
+        ("notes", entry.notes.as_deref()),
+    ]
+    .into_iter()
+    .find(|(_, value)| value.is_some_and(|v| !match_ranges(v, query).is_empty()))
+    .map(|(field, _)| field)
+}
+
+fn search_style() -> Style {
+    Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD)
+}
+
+/// `text` as spans, with every match of `query` in the search style.
+fn highlighted_spans(text: &str, query: Option<&str>) -> Vec<Span<'static>> {
+    let ranges = query.map(|q| match_ranges(text, q)).unwrap_or_default();
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for (start, end) in ranges {
+        if start > last {
+            spans.push(Span::raw(text[last..start].to_string()));
+        }
+        spans.push(Span::styled(text[start..end].to_string(), search_style()));
+        last = end;
+    }
+    if last < text.len() || spans.is_empty() {
+        spans.push(Span::raw(text[last..].to_string()));
+    }
+    spans
+}
+
+/// Centered inside a bordered pane while the vault has nothing to list, so a
+/// new user sees which key starts things off.
+fn render_empty_hint(f: &mut Frame<'_>, pane: Rect, hint: &str) {
+    let inner = pane.inner(Margin::new(1, 1));
+    let height = inner.height.min(2);
+    let area = Rect {
+        y: inner.y + inner.height.saturating_sub(height) / 2,
+        height,
+        ..inner
+    };
+    let paragraph = Paragraph::new(hint)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(COLOR_SAND).add_modifier(Modifier::BOLD));
+    f.render_widget(paragraph, area);
+}
+
+/// Below this the three side-by-side panes are too cramped to read.
+const COMPACT_MAX_WIDTH: u16 = 60;
+const COMPACT_MAX_HEIGHT: u16 = 12;
+/// Smallest frame the banner unlock screen fits in; anything smaller gets the
+/// minimal one.
+const FULL_UNLOCK_MIN_WIDTH: u16 = 52;
+const FULL_UNLOCK_MIN_HEIGHT: u16 = 20;
+
+/// Whether `draw` stacks its panes and shows one at a time for this frame.
+pub fn is_compact(size: Rect) -> bool {
+    size.width < COMPACT_MAX_WIDTH || size.height < COMPACT_MAX_HEIGHT
+}
+
+/// Returns the detail scroll actually used, which stops at the last line.
+pub fn draw(f: &mut Frame<'_>, state: &ViewState) -> u16 {
+    let compact = is_compact(f.size());
     let layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .constraints([Constraint::Min(1), Constraint::Length(if compact { 1 } else { 3 })])
         .split(f.size());
 
-    let body = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(30), // services
-            Constraint::Percentage(30), // creds
-            Constraint::Percentage(40), // detail
-        ])
-        .split(layout[0]);
+    // Services, credentials, detail; the compact layout only gives the focused one an area.
+    let panes: [Option<Rect>; 3] = if compact {
+        let mut panes = [None; 3];
+        let focused = if state.focus_services {
+            0
+        } else if state.show_detail {
+            2
+        } else {
+            1
+        };
+        panes[focused] = Some(layout[0]);
+        panes
+    } else {
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(30), // services
+                Constraint::Percentage(30), // creds
+                Constraint::Percentage(40), // detail
+            ])
+            .split(layout[0]);
+        [Some(body[0]), Some(body[1]), Some(body[2])]
+    };
+    let pane_title = |title: &str, pos: usize| {
+        if compact {
+            format!("{title} ({pos}/3, Tab next)")
+        } else {
+            title.to_string()
+        }
+    };
 
     // Services list
     let services_items: Vec<ListItem> = if state.services.is_empty() {
@@ -292,7 +635,7 @@ pub fn draw(f: &mut Frame<'_>, state: &ViewState) {
         state
             .services
             .iter()
-            .map(|s| ListItem::new(s.clone()))
+            .map(|s| ListItem::new(Line::from(highlighted_spans(s, state.search))))
             .collect()
     };
     let mut svc_state = ListState::default();
@@ -300,7 +643,7 @@ pub fn draw(f: &mut Frame<'_>, state: &ViewState) {
         svc_state.select(Some(state.service_idx.min(state.services.len() - 1)));
     }
     let svc_list = List::new(services_items)
-        .block(Block::default().title("Services").borders(Borders::ALL))
+        .block(Block::default().title(pane_title("Services", 1)).borders(Borders::ALL))
         .highlight_symbol("▶ ")
         .highlight_style(if state.focus_services {
             Style::default()
@@ -311,7 +654,9 @@ pub fn draw(f: &mut Frame<'_>, state: &ViewState) {
             Style::default()
                 .fg(Color::DarkGray)
         });
-    f.render_stateful_widget(svc_list, body[0], &mut svc_state);
+    if let Some(area) = panes[0] {
+        f.render_stateful_widget(svc_list, area, &mut svc_state);
+    }
 
     // Entries under selected service
     let current_service = state
@@ -332,19 +677,34 @@ pub fn draw(f: &mut Frame<'_>, state: &ViewState) {
         filtered
             .iter()
             .map(|e| {
-                let user = e
-                    .username
-                    .as_deref()
-                    .unwrap_or_else(|| if e.email.is_empty() { "-" } else { &e.email });
-                let strength = classify_password_strength(&e.password);
-                let color = strength_color(strength.level);
-                ListItem::new(Line::from(vec![
-                    Span::raw(format!("{user} ({}) ", e.email)),
-                    Span::styled(
-                        format!("[{}]", strength.label),
-                        Style::default().fg(color).add_modifier(Modifier::BOLD),
-                    ),
-                ]))
+                if let Some(card) = &e.card {
+                    let mut spans = highlighted_spans(&card.cardholder, state.search);
+                    spans.push(Span::raw(format!(" {} ", card.masked_number(state.mask_char))));
+                    spans.push(Span::styled("[Card]", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)));
+                    return ListItem::new(Line::from(spans));
+                }
+                let (badge, color) = if e.entry_type == EntryType::Login {
+                    let strength = state.strength_cache.get(e);
+                    (strength.label, strength_color(strength.level))
+                } else {
+                    (e.entry_type.label().to_string(), Color::Cyan)
+                };
+                let label = state.entry_label.render(e);
+                let mut spans = highlighted_spans(&label, state.search);
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(
+                    format!("[{badge}]"),
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                ));
+                // A match the label doesn't show (notes, or a field the
+                // template leaves out) is named after the badge.
+                if let Some(query) = state.search
+                    && match_ranges(&label, query).is_empty()
+                    && let Some(field) = hidden_match_field(e, query)
+                {
+                    spans.push(Span::styled(format!(" {field}"), search_style()));
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect()
     };
@@ -354,7 +714,7 @@ pub fn draw(f: &mut Frame<'_>, state: &ViewState) {
         entry_state.select(Some(state.entry_idx.min(filtered.len() - 1)));
     }
     let entry_list = List::new(entry_items)
-        .block(Block::default().title("Credentials").borders(Borders::ALL))
+        .block(Block::default().title(pane_title("Credentials", 2)).borders(Borders::ALL))
         .highlight_symbol("▶ ")
         .highlight_style(if !state.focus_services {
             Style::default()
@@ -364,25 +724,51 @@ pub fn draw(f: &mut Frame<'_>, state: &ViewState) {
         } else {
             Style::default().fg(Color::DarkGray)
         });
-    f.render_stateful_widget(entry_list, body[1], &mut entry_state);
+    if let Some(area) = panes[1] {
+        f.render_stateful_widget(entry_list, area, &mut entry_state);
+    }
 
     // Detail pane
     let detail_block = Block::default()
-        .title(format!("Details: {current_service}"))
+        .title(pane_title(&format!("Details: {current_service}"), 3))
         .borders(Borders::ALL);
-    let detail_lines = if let Some(entry) = filtered.get(state.entry_idx.min(filtered.len().saturating_sub(1))) {
+    let detail_width = panes[2].map_or(0, |area| usize::from(area.width.saturating_sub(2)));
+    let selected = filtered.get(state.entry_idx.min(filtered.len().saturating_sub(1)));
+    let mut detail_lines = if let Some(card) = selected.and_then(|e| e.card.as_ref()) {
+        let entry = selected.expect("card belongs to the selected entry");
+        let mut lines = vec![
+            Line::from(format!("Service: {}", entry.name)),
+            Line::from(format!("Type: {}", entry.entry_type.label())),
+            Line::from(format!("Cardholder: {}", card.cardholder)),
+            Line::from(format!("Number: {} (c copies after confirming)", card.masked_number(state.mask_char))),
+            Line::from(format!("Expiry: {}", card.expiry)),
+            Line::from("CVV: (hidden)"),
+        ];
+        lines.extend(notes_lines(entry.notes.as_deref(), detail_width));
+        for field in &entry.fields {
+            if field.secret {
+                lines.push(Line::from(format!("{}: (hidden)", field.label)));
+            } else {
+                lines.extend(value_lines(&field.label, field.value.expose(), detail_width));
+            }
+        }
+        lines
+    } else if let Some(entry) = selected {
         let user = entry.username.as_deref().unwrap_or("-");
-        let notes = entry.notes.as_deref().unwrap_or("-");
         let strength = state
             .detail_strength_override
             .clone()
-            .unwrap_or_else(|| classify_password_strength(&entry.password));
+            .unwrap_or_else(|| state.strength_cache.get(entry));
         let color = strength_color(strength.level);
-        vec![
+        let secret_label = entry.entry_type.secret_label();
+        let mut lines = vec![
             Line::from(format!("Service: {}", entry.name)),
+            Line::from(format!("Type: {}", entry.entry_type.label())),
             Line::from(format!("Username: {user}")),
             Line::from(format!("Email: {}", entry.email)),
-            Line::from(format!("Notes: {notes}")),
+        ];
+        lines.extend(notes_lines(entry.notes.as_deref(), detail_width));
+        lines.extend([
             Line::from(vec![
                 Span::raw("Strength: "),
                 Span::styled(
@@ -390,13 +776,73 @@ pub fn draw(f: &mut Frame<'_>, state: &ViewState) {
                     Style::default().fg(color).add_modifier(Modifier::BOLD),
                 ),
             ]),
-            Line::from("Password: (hidden)"),
-        ]
+            Line::from(if entry.sensitive {
+                format!("{secret_label}: (hidden, sensitive: c asks first)")
+            } else {
+                format!("{secret_label}: (hidden)")
+            }),
+        ]);
+        if let Some(key) = &entry.ssh_key {
+            lines.push(Line::from("Private key: (hidden; K copies)"));
+            if !key.public_key.is_empty() {
+                lines.extend(value_lines("Public key", &key.public_key, detail_width));
+            }
+        }
+        if let Some(updated_at) = entry.updated_at {
+            let days = unix_now().saturating_sub(updated_at) / 86_400;
+            let text = format!(
+                "{secret_label} set {days} day{} ago",
+                if days == 1 { "" } else { "s" }
+            );
+            lines.push(if days > state.password_age_warn_days {
+                Line::from(Span::styled(
+                    text,
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ))
+            } else {
+                Line::from(text)
+            });
+        }
+        for field in &entry.fields {
+            if field.secret {
+                lines.push(Line::from(format!("{}: (hidden)", field.label)));
+            } else {
+                lines.extend(value_lines(&field.label, field.value.expose(), detail_width));
+            }
+        }
+        if !entry.attachments.is_empty() {
+            let names: Vec<&str> = entry.attachments.iter().map(|a| a.name.as_str()).collect();
+            lines.push(Line::from(format!("Attachments: {}", names.join(", "))));
+        }
+        if let Some(secret) = entry.totp_secret.as_ref().map(SecretString::expose) {
+            lines.push(match crate::totp::current_code(secret) {
+                Ok((code, remaining)) => Line::from(format!("TOTP: {code} ({remaining}s)")),
+                Err(e) => Line::from(format!("TOTP: {e}")),
+            });
+        }
+        lines
     } else {
         vec![Line::from("No credential selected.")]
     };
-    let detail = Paragraph::new(detail_lines).wrap(Wrap { trim: true }).block(detail_block);
-    f.render_widget(detail, body[2]);
+    if state.show_entry_id
+        && let Some(entry) = selected
+    {
+        detail_lines.push(Line::from(format!("ID: {}", entry.id)));
+    }
+    // No trimming: indentation and runs of spaces in notes are intentional.
+    let scroll = state.detail_scroll.min(u16::try_from(detail_lines.len().saturating_sub(1)).unwrap_or(u16::MAX));
+    let detail = Paragraph::new(detail_lines)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+        .block(detail_block);
+    if let Some(area) = panes[2] {
+        f.render_widget(detail, area);
+    }
+    if state.vault.entries.is_empty()
+        && let Some(area) = panes[2].or(panes.into_iter().flatten().next())
+    {
+        render_empty_hint(f, area, "Press n to add your first credential");
+    }
 
     let footer_line = if let Some(strength) = &state.status_strength {
         let level = strength.level.clamp(1, 4);
@@ -418,9 +864,21 @@ pub fn draw(f: &mut Frame<'_>, state: &ViewState) {
             Span::raw("]"),
         ])
     } else {
-        Line::from(state.status.clone())
+        let mut spans = vec![Span::raw(state.status.clone())];
+        if state.clipboard_countdown
+            && let Some((remaining, total)) = clipboard_countdown()
+        {
+            spans.extend(countdown_spans(remaining, total));
+        }
+        Line::from(spans)
+    };
+    // A one-row footer without a border leaves short frames room for the pane.
+    let footer_block = if compact {
+        Block::default().borders(Borders::NONE)
+    } else {
+        Block::default().borders(Borders::ALL).title(revision_title(state.vault))
     };
-    let footer = Paragraph::new(footer_line).block(Block::default().borders(Borders::ALL));
+    let footer = Paragraph::new(footer_line).block(footer_block);
     f.render_widget(footer, layout[1]);
 
     if let Some(lines) = &state.overlay {
@@ -440,9 +898,15 @@ pub fn draw(f: &mut Frame<'_>, state: &ViewState) {
         ];
         render_overlay(f, &text, "Confirm delete");
     }
+    scroll
 }
 
 pub fn draw_unlock(f: &mut Frame<'_>, state: &UnlockState) {
+    let size = f.size();
+    if state.minimal || size.width < FULL_UNLOCK_MIN_WIDTH || size.height < FULL_UNLOCK_MIN_HEIGHT {
+        draw_unlock_minimal(f, state);
+        return;
+    }
     let layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -487,6 +951,55 @@ pub fn draw_unlock(f: &mut Frame<'_>, state: &UnlockState) {
     let label_area = Rect::new(x, input_area.y, w, 1);
     let box_area = Rect::new(x, input_area.y + 1, w, 3);
 
+    render_unlock_input(f, state, label_area, box_area);
+
+    let footer =
+        Paragraph::new(state.status.clone()).block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, layout[5]);
+
+    // Animation beneath the input box
+    let anim = VAULT_FRAMES[state.anim_frame % VAULT_FRAMES.len()];
+    let anim_width: u16 = anim.iter().map(|l| l.len() as u16).max().unwrap_or(0);
+    let anim_height: u16 = anim.len() as u16;
+    let area = layout[3];
+    let x = area.x + area.width.saturating_sub(anim_width) / 2;
+    let y = area.y + area.height.saturating_sub(anim_height) / 2;
+    let anim_area = Rect::new(x, y, anim_width, anim_height);
+    let anim_lines: Vec<Line> = anim
+        .iter()
+        .map(|l| Line::from(Span::styled(*l, Style::default().fg(COLOR_SAND))))
+        .collect();
+    let anim_paragraph = Paragraph::new(anim_lines).alignment(Alignment::Center);
+    f.render_widget(anim_paragraph, anim_area);
+}
+
+/// Passphrase box and footer only, for `minimal_unlock` and for terminals too
+/// small for the banner. Needs just seven rows.
+fn draw_unlock_minimal(f: &mut Frame<'_>, state: &UnlockState) {
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // spacer
+            Constraint::Length(4),  // input area
+            Constraint::Min(0),     // spacer
+            Constraint::Length(3),  // footer
+        ])
+        .split(f.size());
+
+    let box_width: u16 = 40;
+    let input_area = layout[1];
+    let w = box_width.min(input_area.width);
+    let x = input_area.x + input_area.width.saturating_sub(w) / 2;
+    let label_area = Rect::new(x, input_area.y, w, 1);
+    let box_area = Rect::new(x, input_area.y + 1, w, input_area.height.saturating_sub(1));
+    render_unlock_input(f, state, label_area, box_area);
+
+    let footer =
+        Paragraph::new(state.status.clone()).block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, layout[3]);
+}
+
+fn render_unlock_input(f: &mut Frame<'_>, state: &UnlockState, label_area: Rect, box_area: Rect) {
     let label = Paragraph::new("Enter the passphrase")
         .alignment(Alignment::Center)
         .style(Style::default().fg(COLOR_SAND).add_modifier(Modifier::BOLD));
@@ -505,25 +1018,46 @@ pub fn draw_unlock(f: &mut Frame<'_>, state: &UnlockState) {
         }),
     );
     f.render_widget(prompt, box_area);
+}
 
-    let footer =
-        Paragraph::new(state.status.clone()).block(Block::default().borders(Borders::ALL));
-    f.render_widget(footer, layout[5]);
+const MIN_NOTES_LIST_PERCENT: u16 = 20;
+const MAX_NOTES_LIST_PERCENT: u16 = 70;
 
-    // Animation beneath the input box
-    let anim = VAULT_FRAMES[state.anim_frame % VAULT_FRAMES.len()];
-    let anim_width: u16 = anim.iter().map(|l| l.len() as u16).max().unwrap_or(0);
-    let anim_height: u16 = anim.len() as u16;
-    let area = layout[3];
-    let x = area.x + area.width.saturating_sub(anim_width) / 2;
-    let y = area.y + area.height.saturating_sub(anim_height) / 2;
-    let anim_area = Rect::new(x, y, anim_width, anim_height);
-    let anim_lines: Vec<Line> = anim
-        .iter()
-        .map(|l| Line::from(Span::styled(*l, Style::default().fg(COLOR_SAND))))
-        .collect();
-    let anim_paragraph = Paragraph::new(anim_lines).alignment(Alignment::Center);
-    f.render_widget(anim_paragraph, anim_area);
+/// Content pane share of the notes screen for a configured list percentage.
+fn notes_content_percent(list_percent: u16) -> u16 {
+    100 - list_percent.clamp(MIN_NOTES_LIST_PERCENT, MAX_NOTES_LIST_PERCENT)
+}
+
+/// Largest useful scroll offset for the note content pane at the given frame
+/// width, counting wrapped rows so long paragraphs can be scrolled through.
+pub fn note_scroll_limit(note: Option<&Note>, frame_width: u16, list_percent: u16) -> u16 {
+    let Some(note) = note else {
+        return 0;
+    };
+    let pane_width = frame_width as usize * notes_content_percent(list_percent) as usize / 100;
+    let inner = pane_width.saturating_sub(2).max(1);
+    let rows: usize = note_detail_text(note)
+        .lines()
+        .map(|l| l.chars().count().div_ceil(inner).max(1))
+        .sum();
+    rows.saturating_sub(1).min(u16::MAX as usize) as u16
+}
+
+fn note_detail_text(note: &Note) -> String {
+    format!("Title: {}\n{}\n\n{}", note.title, note_metadata(note), note.content.expose())
+}
+
+/// Size of a note at a glance, e.g. to check a list of recovery codes is
+/// complete. A trailing newline doesn't count as another line.
+fn note_metadata(note: &Note) -> String {
+    let content = note.content.expose();
+    let chars = content.chars().count();
+    let lines = content.lines().count();
+    format!(
+        "{chars} char{} · {lines} line{}",
+        if chars == 1 { "" } else { "s" },
+        if lines == 1 { "" } else { "s" }
+    )
 }
 
 pub fn draw_notes(f: &mut Frame<'_>, state: &NoteViewState) {
@@ -534,7 +1068,10 @@ pub fn draw_notes(f: &mut Frame<'_>, state: &NoteViewState) {
 
     let body = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .constraints([
+            Constraint::Percentage(100 - notes_content_percent(state.list_percent)),
+            Constraint::Percentage(notes_content_percent(state.list_percent)),
+        ])
         .split(layout[0]);
 
     // Notes list
@@ -566,14 +1103,21 @@ pub fn draw_notes(f: &mut Frame<'_>, state: &NoteViewState) {
     // Detail
     let detail_block = Block::default().title("Content").borders(Borders::ALL);
     let detail_text = if let Some(note) = state.vault.notes.get(state.note_idx.min(state.vault.notes.len().saturating_sub(1))) {
-        format!("Title: {}\n\n{}", note.title, note.content)
+        note_detail_text(note)
     } else {
         "No note selected.".to_string()
     };
-    let detail = Paragraph::new(detail_text).wrap(Wrap { trim: true }).block(detail_block);
+    let detail = Paragraph::new(detail_text)
+        .wrap(Wrap { trim: true })
+        .scroll((state.content_scroll, 0))
+        .block(detail_block);
     f.render_widget(detail, body[1]);
+    if state.vault.notes.is_empty() {
+        render_empty_hint(f, body[1], "Press n to add your first note");
+    }
 
-    let footer = Paragraph::new(state.status.clone()).block(Block::default().borders(Borders::ALL));
+    let footer = Paragraph::new(state.status.clone())
+        .block(Block::default().borders(Borders::ALL).title(revision_title(state.vault)));
     f.render_widget(footer, layout[1]);
 
     if let Some(lines) = &state.add_overlay {
@@ -590,21 +1134,550 @@ pub fn draw_notes(f: &mut Frame<'_>, state: &NoteViewState) {
     }
 }
 
-pub fn copy_password_to_clipboard(entry: &Entry) -> Result<()> {
-    let mut clipboard = Clipboard::new().map_err(|e| anyhow!("Clipboard unavailable: {e}"))?;
-    clipboard
-        .set_text(entry.password.clone())
-        .map_err(|e| anyhow!("Failed to set clipboard: {e}"))?;
-    let mut clip = clipboard;
+/// Columns a tab takes in the built-in note editor.
+const EDITOR_TAB_WIDTH: usize = 4;
+
+/// Text buffer for the built-in note editor, used when no external editor
+/// starts. The cursor column counts chars, not bytes.
+pub struct NoteEditor {
+    pub title: String,
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+    scroll: (u16, u16),
+    modified: bool,
+}
+
+impl NoteEditor {
+    pub fn new(title: &str, content: &str) -> Self {
+        NoteEditor {
+            title: title.to_string(),
+            lines: content.split('\n').map(str::to_string).collect(),
+            row: 0,
+            col: 0,
+            scroll: (0, 0),
+            modified: false,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    fn byte_col(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices().nth(self.col).map_or(line.len(), |(i, _)| i)
+    }
+
+    fn line_chars(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let at = self.byte_col();
+        self.lines[self.row].insert(at, c);
+        self.col += 1;
+        self.modified = true;
+    }
+
+    /// Pasted text keeps its line breaks; `\r` and other control characters
+    /// apart from tabs are dropped.
+    pub fn insert_text(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\n' => self.newline(),
+                '\t' => self.insert_char(c),
+                c if c.is_control() => {}
+                c => self.insert_char(c),
+            }
+        }
+    }
+
+    pub fn newline(&mut self) {
+        let at = self.byte_col();
+        let rest = self.lines[self.row].split_off(at);
+        self.row += 1;
+        self.lines.insert(self.row, rest);
+        self.col = 0;
+        self.modified = true;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let at = self.byte_col();
+            self.lines[self.row].remove(at);
+            self.modified = true;
+        } else if self.row > 0 {
+            let mut line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_chars();
+            self.lines[self.row].push_str(&line);
+            line.zeroize();
+            self.modified = true;
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_chars();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.col < self.line_chars() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.row > 0 {
+            self.row -= 1;
+            self.col = self.col.min(self.line_chars());
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = self.col.min(self.line_chars());
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.col = self.line_chars();
+    }
+
+    /// Screen column of the cursor, with tabs expanded.
+    fn cursor_x(&self) -> usize {
+        self.lines[self.row]
+            .chars()
+            .take(self.col)
+            .map(|c| if c == '\t' { EDITOR_TAB_WIDTH } else { 1 })
+            .sum()
+    }
+
+    /// Moves the scroll offset just enough to keep the cursor inside a
+    /// `width` x `height` view.
+    fn follow_cursor(&mut self, width: u16, height: u16) {
+        let (mut top, mut left) = (self.scroll.0 as usize, self.scroll.1 as usize);
+        let (height, width) = (height.max(1) as usize, width.max(1) as usize);
+        if self.row < top {
+            top = self.row;
+        } else if self.row >= top + height {
+            top = self.row + 1 - height;
+        }
+        let x = self.cursor_x();
+        if x < left {
+            left = x;
+        } else if x >= left + width {
+            left = x + 1 - width;
+        }
+        self.scroll = (top.min(u16::MAX as usize) as u16, left.min(u16::MAX as usize) as u16);
+    }
+}
+
+impl Drop for NoteEditor {
+    fn drop(&mut self) {
+        self.lines.iter_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+/// Full-screen view of the built-in note editor. `message` is shown above the
+/// key hints (e.g. why no external editor was used); `discard_overlay` asks
+/// before dropping changes.
+pub fn draw_note_editor(f: &mut Frame<'_>, editor: &mut NoteEditor, message: &str, discard_overlay: bool) {
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(4)])
+        .split(f.size());
+
+    let body = layout[0];
+    editor.follow_cursor(body.width.saturating_sub(2), body.height.saturating_sub(2));
+    let tab = " ".repeat(EDITOR_TAB_WIDTH);
+    let text: Vec<Line> = editor.lines.iter().map(|l| Line::from(l.replace('\t', &tab))).collect();
+    let marker = if editor.modified { " [modified]" } else { "" };
+    let paragraph = Paragraph::new(text)
+        .scroll(editor.scroll)
+        .block(Block::default().title(format!("Edit note: {}{marker}", editor.title)).borders(Borders::ALL));
+    f.render_widget(paragraph, body);
+
+    let footer = Paragraph::new(vec![
+        Line::from(message.to_string()),
+        Line::from(format!(
+            "Ctrl+s save · Esc cancel · Ln {}, Col {}",
+            editor.row + 1,
+            editor.col + 1
+        )),
+    ])
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, layout[1]);
+
+    if discard_overlay {
+        let lines = vec![
+            "Discard changes to this note?".to_string(),
+            "".to_string(),
+            "[y] Discard   [n] Keep editing".to_string(),
+        ];
+        render_overlay(f, &lines, "Confirm quit");
+    } else {
+        let x = editor.cursor_x().saturating_sub(editor.scroll.1 as usize);
+        let y = editor.row.saturating_sub(editor.scroll.0 as usize);
+        f.set_cursor(body.x + 1 + x as u16, body.y + 1 + y as u16);
+    }
+}
+
+pub fn copy_password_to_clipboard(entry: &Entry, clear_after_secs: u64) -> Result<()> {
+    copy_with_auto_clear(entry.password.expose().to_string(), clear_after_secs)
+}
+
+pub fn copy_field_to_clipboard(field: &CustomField, clear_after_secs: u64) -> Result<()> {
+    copy_with_auto_clear(field.value.expose().to_string(), clear_after_secs)
+}
+
+pub fn copy_private_key_to_clipboard(key: &SshKey, clear_after_secs: u64) -> Result<()> {
+    copy_with_auto_clear(key.private_key.expose().to_string(), clear_after_secs)
+}
+
+pub fn copy_card_number_to_clipboard(card: &Card, clear_after_secs: u64) -> Result<()> {
+    copy_with_auto_clear(card.number.expose().to_string(), clear_after_secs)
+}
+
+pub fn copy_totp_to_clipboard(code: &str, clear_after_secs: u64) -> Result<()> {
+    copy_with_auto_clear(code.to_string(), clear_after_secs)
+}
+
+pub fn copy_text_to_clipboard(text: &str, clear_after_secs: u64) -> Result<()> {
+    copy_with_auto_clear(text.to_string(), clear_after_secs)
+}
+
+const CLIPBOARD_POLL_MS: u64 = 250;
+
+// Set while the clipboard still holds something we copied, so teardown only
+// wipes our own content.
+static CLIPBOARD_WRITTEN: AtomicBool = AtomicBool::new(false);
+// Bumped per copy; a watcher stands down once a newer copy owns the clipboard.
+static CLIPBOARD_GENERATION: AtomicU64 = AtomicU64::new(0);
+// `use_osc52` from the config, set once at startup.
+static PREFER_OSC52: AtomicBool = AtomicBool::new(false);
+// Whether the latest copy went through OSC 52, so clearing goes the same way.
+static LAST_COPY_OSC52: AtomicBool = AtomicBool::new(false);
+// When the latest copy clears, and the full timeout, for the footer countdown.
+static CLIPBOARD_DEADLINE: Mutex<Option<(Instant, Duration)>> = Mutex::new(None);
+
+fn set_clipboard_deadline(clear_after_secs: u64) {
+    let timeout = Duration::from_secs(clear_after_secs);
+    *CLIPBOARD_DEADLINE.lock().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now() + timeout, timeout));
+}
+
+/// Time left before our copy is wiped and the full timeout, while the
+/// clipboard still holds it.
+pub fn clipboard_countdown() -> Option<(Duration, Duration)> {
+    if !CLIPBOARD_WRITTEN.load(Ordering::SeqCst) {
+        return None;
+    }
+    let (deadline, timeout) = (*CLIPBOARD_DEADLINE.lock().unwrap_or_else(|e| e.into_inner()))?;
+    let remaining = deadline.checked_duration_since(Instant::now())?;
+    Some((remaining, timeout))
+}
+
+pub fn set_prefer_osc52(enabled: bool) {
+    PREFER_OSC52.store(enabled, Ordering::SeqCst);
+}
+
+pub const CLIPBOARD_DISABLED: &str = "Clipboard disabled (clipboard_enabled = false)";
+
+static CLIPBOARD_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// With the clipboard disabled every copy fails with [`CLIPBOARD_DISABLED`]
+/// before touching the system clipboard or the terminal.
+pub fn set_clipboard_enabled(enabled: bool) {
+    CLIPBOARD_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn clipboard_enabled() -> bool {
+    CLIPBOARD_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Sets the terminal's clipboard with an OSC 52 escape; an empty `text` clears
+/// it. Works over SSH when the local terminal allows clipboard writes.
+fn write_osc52(text: &str) -> Result<()> {
+    let mut encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut sequence = format!("\x1b]52;c;{encoded}\x07");
+    encoded.zeroize();
+    let mut out = io::stdout().lock();
+    let written = out.write_all(sequence.as_bytes()).and_then(|_| out.flush());
+    sequence.zeroize();
+    written.map_err(|e| anyhow!("Failed to write OSC 52 sequence: {e}"))
+}
+
+/// OSC 52 has no reliable way to read the clipboard back, so the watcher can't
+/// notice a newer copy made elsewhere; it clears at the deadline unless a later
+/// copy from this app took over.
+fn copy_via_osc52(mut text: String, clear_after_secs: u64) -> Result<()> {
+    let written = write_osc52(&text);
+    text.zeroize();
+    written?;
+    let generation = CLIPBOARD_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    LAST_COPY_OSC52.store(true, Ordering::SeqCst);
+    set_clipboard_deadline(clear_after_secs);
+    CLIPBOARD_WRITTEN.store(true, Ordering::SeqCst);
     thread::spawn(move || {
-        thread::sleep(Duration::from_secs(CLIPBOARD_LIFETIME_SECS));
-        let _ = clip.set_text(String::new());
+        thread::sleep(Duration::from_secs(clear_after_secs));
+        if CLIPBOARD_GENERATION.load(Ordering::SeqCst) == generation {
+            let _ = write_osc52("");
+            CLIPBOARD_WRITTEN.store(false, Ordering::SeqCst);
+        }
     });
     Ok(())
 }
 
-pub fn prompt_new_entry() -> Result<Entry> {
+/// Copies `text` and watches the clipboard: the secret is cleared after
+/// `clear_after_secs` at the latest. The watcher stops early once the content
+/// changes (pasted, then something else copied). It leaves that newer content
+/// alone, because the secret is already gone. Falls back to OSC 52 when the
+/// system clipboard can't be reached.
+fn copy_with_auto_clear(mut text: String, clear_after_secs: u64) -> Result<()> {
+    if !clipboard_enabled() {
+        text.zeroize();
+        return Err(anyhow!(CLIPBOARD_DISABLED));
+    }
+    if PREFER_OSC52.load(Ordering::SeqCst) {
+        return copy_via_osc52(text, clear_after_secs);
+    }
+    let (done, reply) = mpsc::channel();
+    let generation = CLIPBOARD_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let request = ClipboardRequest::Copy {
+        text: text.clone(),
+        generation,
+        deadline: Instant::now() + Duration::from_secs(clear_after_secs),
+        done,
+    };
+    let copied = clipboard_owner().send(request).is_ok() && reply.recv().unwrap_or(false);
+    if copied {
+        text.zeroize();
+        LAST_COPY_OSC52.store(false, Ordering::SeqCst);
+        set_clipboard_deadline(clear_after_secs);
+        CLIPBOARD_WRITTEN.store(true, Ordering::SeqCst);
+        Ok(())
+    } else {
+        copy_via_osc52(text, clear_after_secs)
+    }
+}
+
+/// Longest `clear_clipboard` waits for the owner thread to confirm the wipe.
+const CLIPBOARD_CLEAR_WAIT_MS: u64 = 500;
+
+enum ClipboardRequest {
+    Copy {
+        text: String,
+        generation: u64,
+        deadline: Instant,
+        done: mpsc::Sender<bool>,
+    },
+    Clear {
+        done: mpsc::Sender<()>,
+    },
+}
+
+/// A secret the owner thread put on the clipboard and still watches.
+struct HeldSecret {
+    text: String,
+    generation: u64,
+    deadline: Instant,
+}
+
+#[derive(Debug, PartialEq)]
+enum WatchStep {
+    /// Still ours and not yet due.
+    Keep,
+    /// The content changed, so the secret is gone already; leave it alone.
+    Replaced,
+    /// Due: wipe it.
+    Expired,
+}
+
+/// One poll of a held secret. A read error other than "no text" (e.g. the
+/// clipboard is busy) keeps watching rather than guessing.
+fn watch_step(
+    held: &str,
+    read: std::result::Result<String, arboard::Error>,
+    now: Instant,
+    deadline: Instant,
+) -> WatchStep {
+    if now >= deadline {
+        return WatchStep::Expired;
+    }
+    match read {
+        Ok(mut current) => {
+            let changed = current != held;
+            current.zeroize();
+            if changed { WatchStep::Replaced } else { WatchStep::Keep }
+        }
+        Err(arboard::Error::ContentNotAvailable) => WatchStep::Replaced,
+        Err(_) => WatchStep::Keep,
+    }
+}
+
+/// Whether the system clipboard still shows the held secret. A failed read
+/// counts as no, as there is nothing to compare.
+fn still_holds(held: &str, read: std::result::Result<String, arboard::Error>) -> bool {
+    match read {
+        Ok(mut current) => {
+            let same = current == held;
+            current.zeroize();
+            same
+        }
+        Err(_) => false,
+    }
+}
+
+/// Sender for the thread that owns the system clipboard for the rest of the
+/// process, started on first use.
+///
+/// On X11 and Wayland the clipboard content lives in the process that set it
+/// and is served from there; dropping the last `Clipboard` hands it to a
+/// clipboard manager, if one runs. A detached per-copy timer could therefore
+/// die at exit with the secret still held, or get handed over. Keeping one
+/// owner alive and having teardown ask it to wipe (see `clear_clipboard`)
+/// makes the clear happen before the process goes away. macOS and Windows
+/// keep the clipboard in the OS, where the single owner thread also avoids
+/// clipboard access from several threads at once.
+fn clipboard_owner() -> &'static mpsc::Sender<ClipboardRequest> {
+    static OWNER: OnceLock<mpsc::Sender<ClipboardRequest>> = OnceLock::new();
+    OWNER.get_or_init(|| {
+        let (requests, incoming) = mpsc::channel();
+        thread::spawn(move || run_clipboard_owner(incoming));
+        requests
+    })
+}
+
+fn run_clipboard_owner(incoming: mpsc::Receiver<ClipboardRequest>) {
+    let mut clipboard = Clipboard::new().ok();
+    let mut held: Option<HeldSecret> = None;
+    loop {
+        let request = if held.is_some() {
+            match incoming.recv_timeout(Duration::from_millis(CLIPBOARD_POLL_MS)) {
+                Ok(request) => Some(request),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        } else {
+            match incoming.recv() {
+                Ok(request) => Some(request),
+                Err(_) => return,
+            }
+        };
+        match request {
+            Some(ClipboardRequest::Copy { text, generation, deadline, done }) => {
+                if clipboard.is_none() {
+                    clipboard = Clipboard::new().ok();
+                }
+                let set = clipboard
+                    .as_mut()
+                    .is_some_and(|clip| clip.set_text(text.clone()).is_ok());
+                if let Some(mut old) = held.take() {
+                    old.text.zeroize();
+                }
+                if set {
+                    held = Some(HeldSecret { text, generation, deadline });
+                } else {
+                    let mut text = text;
+                    text.zeroize();
+                }
+                let _ = done.send(set);
+            }
+            Some(ClipboardRequest::Clear { done }) => {
+                if let Some(mut old) = held.take() {
+                    if let Some(clip) = clipboard.as_mut() {
+                        let _ = clip.set_text(String::new());
+                    }
+                    old.text.zeroize();
+                }
+                let _ = done.send(());
+            }
+            None => {}
+        }
+        let Some(secret) = held.as_mut() else {
+            continue;
+        };
+        let owned = CLIPBOARD_GENERATION.load(Ordering::SeqCst) == secret.generation;
+        let step = match clipboard.as_mut() {
+            Some(clip) if owned => watch_step(&secret.text, clip.get_text(), Instant::now(), secret.deadline),
+            // A later OSC 52 copy took over and its own timer clears the
+            // terminal's clipboard, but the system one may still hold ours.
+            Some(clip) => {
+                if still_holds(&secret.text, clip.get_text()) {
+                    let _ = clip.set_text(String::new());
+                }
+                WatchStep::Replaced
+            }
+            None => WatchStep::Replaced,
+        };
+        if step == WatchStep::Keep {
+            continue;
+        }
+        if step == WatchStep::Expired
+            && let Some(clip) = clipboard.as_mut()
+        {
+            let _ = clip.set_text(String::new());
+        }
+        if owned {
+            CLIPBOARD_WRITTEN.store(false, Ordering::SeqCst);
+        }
+        if let Some(mut old) = held.take() {
+            old.text.zeroize();
+        }
+    }
+}
+
+/// Wipes the clipboard now rather than waiting for the auto-clear timer, which
+/// would die with the process. System clipboard copies are wiped by the owner
+/// thread, waiting briefly for it so the wipe lands before exit.
+pub fn clear_clipboard() {
+    if !CLIPBOARD_WRITTEN.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    if LAST_COPY_OSC52.load(Ordering::SeqCst) {
+        let _ = write_osc52("");
+        return;
+    }
+    let (done, reply) = mpsc::channel();
+    if clipboard_owner().send(ClipboardRequest::Clear { done }).is_ok() {
+        let _ = reply.recv_timeout(Duration::from_millis(CLIPBOARD_CLEAR_WAIT_MS));
+    }
+}
+
+/// The line prompts need cooked mode. They put back whatever mode the caller
+/// had, so `--no-tui` and the first-run setup stay in cooked mode throughout.
+fn leave_raw_mode() -> bool {
+    let was_raw = is_raw_mode_enabled().unwrap_or(false);
     disable_raw_mode().ok();
+    was_raw
+}
+
+fn restore_raw_mode(was_raw: bool) {
+    if was_raw {
+        enable_raw_mode().ok();
+    }
+}
+
+pub fn prompt_new_entry() -> Result<Entry> {
+    let was_raw = leave_raw_mode();
     let size = crossterm::terminal::size().unwrap_or((80, 24));
     let width = 52u16.min(size.0);
     let xpad = (size.0.saturating_sub(width)) / 2;
@@ -624,7 +1697,7 @@ pub fn prompt_new_entry() -> Result<Entry> {
     let mut name = String::new();
     io::stdin().read_line(&mut name)?;
     if name.trim().eq_ignore_ascii_case("q") {
-        enable_raw_mode().ok();
+        restore_raw_mode(was_raw);
         return Err(anyhow!("cancelled"));
     }
 
@@ -638,7 +1711,7 @@ pub fn prompt_new_entry() -> Result<Entry> {
     let mut email = String::new();
     io::stdin().read_line(&mut email)?;
     if email.trim().eq_ignore_ascii_case("q") || email.trim().is_empty() {
-        enable_raw_mode().ok();
+        restore_raw_mode(was_raw);
         return Err(anyhow!("cancelled"));
     }
 
@@ -650,35 +1723,43 @@ pub fn prompt_new_entry() -> Result<Entry> {
     let prompt_pw = format!("{left}│ Password (hidden): ");
     let password = prompt_password(prompt_pw.as_str())?;
     if password.trim().eq_ignore_ascii_case("q") || password.is_empty() {
-        enable_raw_mode().ok();
+        restore_raw_mode(was_raw);
         return Err(anyhow!("cancelled"));
     }
 
     println!("{left}└{border}┘");
-    enable_raw_mode().ok();
+    restore_raw_mode(was_raw);
 
     Ok(Entry {
         id: crate::models::new_uuid(),
         name: name.trim().to_string(),
         email: email.trim().to_string(),
-        password,
+        password: password.into(),
         username: to_opt(username),
         notes: to_opt(notes),
+        totp_secret: None,
+        updated_at: Some(unix_now()),
+        fields: Vec::new(),
+        attachments: Vec::new(),
+        entry_type: EntryType::Login,
+        ssh_key: None,
+        card: None,
+        sensitive: false,
     })
 }
 
 pub fn prompt_confirm_delete(name: &str) -> Result<bool> {
-    disable_raw_mode().ok();
+    let was_raw = leave_raw_mode();
     print!("Delete '{name}'? (y/N): ");
     io::stdout().flush()?;
     let mut ans = String::new();
     io::stdin().read_line(&mut ans)?;
-    enable_raw_mode().ok();
+    restore_raw_mode(was_raw);
     Ok(matches!(ans.trim().to_lowercase().as_str(), "y" | "yes"))
 }
 
 pub fn prompt_new_master_password() -> Result<String> {
-    disable_raw_mode().ok();
+    let was_raw = leave_raw_mode();
     let pw = loop {
         let p1 = prompt_password("Set a master password: ")?;
         let p2 = prompt_password("Confirm master password: ")?;
@@ -692,15 +1773,15 @@ pub fn prompt_new_master_password() -> Result<String> {
             println!("Passwords did not match, try again.");
         }
     };
-    enable_raw_mode().ok();
+    restore_raw_mode(was_raw);
     Ok(pw)
 }
 
 pub fn prompt_change_master_password() -> Result<String> {
-    disable_raw_mode().ok();
+    let was_raw = leave_raw_mode();
     let p1 = prompt_password("New master password: ")?;
     let p2 = prompt_password("Confirm new master password: ")?;
-    enable_raw_mode().ok();
+    restore_raw_mode(was_raw);
     if p1 != p2 {
         Err(anyhow!("Passwords did not match"))
     } else {
@@ -709,23 +1790,24 @@ pub fn prompt_change_master_password() -> Result<String> {
     }
 }
 
+/// Checks a new master or recovery passphrase against `master_policy`.
 pub fn validate_master_passphrase(passphrase: &str) -> Result<()> {
-    if passphrase.len() < 8 {
-        return Err(anyhow!("Password should be at least 8 characters."));
-    }
-    if !passphrase.chars().any(|c| c.is_ascii_uppercase()) {
-        return Err(anyhow!("Password should include at least one uppercase letter."));
+    let policy = crate::storage::passphrase_policy()?;
+    if passphrase.chars().count() < policy.min_length {
+        return Err(anyhow!("Password should be at least {} characters.", policy.min_length));
     }
-    if !passphrase.chars().any(|c| c.is_ascii_digit()) {
-        return Err(anyhow!("Password should include at least one number."));
+    if let Some(class) = policy.require.iter().find(|class| !passphrase.chars().any(|c| class.matches(c))) {
+        return Err(anyhow!("Password should include at least one {}.", class.label()));
     }
-    if !passphrase
-        .chars()
-        .any(|c| !c.is_alphanumeric() && !c.is_whitespace())
-    {
-        return Err(anyhow!(
-            "Password should include at least one special character."
-        ));
+    if let Some(min) = policy.min_strength {
+        let strength = classify_password_strength(passphrase);
+        if strength.level < min {
+            return Err(anyhow!(
+                "Password strength is {} (level {}); the policy needs level {min} or higher.",
+                strength.label,
+                strength.level
+            ));
+        }
     }
     Ok(())
 }
@@ -740,7 +1822,7 @@ fn to_opt(s: String) -> Option<String> {
 }
 
 pub fn prompt_note(existing: Option<Note>) -> Result<Option<Note>> {
-    disable_raw_mode().ok();
+    let was_raw = leave_raw_mode();
     let mut title = existing.as_ref().map(|n| n.title.clone()).unwrap_or_default();
     let mut content = existing.as_ref().map(|n| n.content.clone()).unwrap_or_default();
 
@@ -754,18 +1836,20 @@ pub fn prompt_note(existing: Option<Note>) -> Result<Option<Note>> {
         title = input.to_string();
     }
     if title.trim().is_empty() {
-        enable_raw_mode().ok();
+        restore_raw_mode(was_raw);
         return Ok(None);
     }
 
     println!("Content (end with a single line containing only . on its own):");
-    if !content.is_empty() {
+    if !content.expose().is_empty() {
         println!("(Existing content will be replaced)");
     }
     let mut lines = Vec::new();
     loop {
         let mut line = String::new();
-        io::stdin().read_line(&mut line)?;
+        if io::stdin().read_line(&mut line)? == 0 {
+            break;
+        }
         let trimmed = line.trim_end();
         if trimmed == "." {
             break;
@@ -773,22 +1857,180 @@ pub fn prompt_note(existing: Option<Note>) -> Result<Option<Note>> {
         lines.push(line);
     }
     if !lines.is_empty() {
-        content = lines.join("");
+        content = lines.join("").into();
     }
 
-    enable_raw_mode().ok();
+    restore_raw_mode(was_raw);
     Ok(Some(Note { id: crate::models::new_uuid(), title, content }))
 }
 
-pub fn copy_note_to_clipboard(note: &Note) -> Result<()> {
-    let mut clipboard = Clipboard::new().map_err(|e| anyhow!("Clipboard unavailable: {e}"))?;
-    clipboard
-        .set_text(note.content.clone())
-        .map_err(|e| anyhow!("Failed to set clipboard: {e}"))?;
-    let mut clip = clipboard;
-    thread::spawn(move || {
-        thread::sleep(Duration::from_secs(CLIPBOARD_LIFETIME_SECS));
-        let _ = clip.set_text(String::new());
-    });
-    Ok(())
+pub fn copy_note_to_clipboard(note: &Note, clear_after_secs: u64) -> Result<()> {
+    copy_with_auto_clear(note.content.expose().to_string(), clear_after_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lines: Vec<Line<'static>>) -> Vec<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn long_note_lines_put_urls_on_their_own_line() {
+        let notes = "Portal:   see https://intranet.example.com/accounts/reset for help\n\n    indented";
+        assert_eq!(
+            texts(notes_lines(Some(notes), 30)),
+            [
+                "Notes:",
+                "  Portal:   see",
+                "  https://intranet.example.com/accounts/reset",
+                "  for help",
+                "  ",
+                "      indented",
+            ]
+        );
+        assert_eq!(texts(notes_lines(Some("short"), 30)), ["Notes: short"]);
+        assert_eq!(
+            texts(value_lines("Login URL", "https://example.com/a/rather/long/path", 30)),
+            ["Login URL:", "  https://example.com/a/rather/long/path"]
+        );
+    }
+
+    #[test]
+    fn note_editor_edits_across_lines() {
+        let mut editor = NoteEditor::new("codes", "ab\ncd");
+        editor.move_down();
+        editor.end();
+        editor.newline();
+        editor.insert_text("e\r\nf\u{7}\tg");
+        assert_eq!(editor.text(), "ab\ncd\ne\nf\tg");
+        editor.home();
+        editor.backspace();
+        editor.backspace();
+        assert_eq!(editor.text(), "ab\ncd\nf\tg");
+        editor.move_up();
+        editor.move_right();
+        editor.insert_char('é');
+        editor.move_left();
+        editor.backspace();
+        assert_eq!(editor.text(), "ab\néd\nf\tg");
+        assert!(editor.is_modified());
+    }
+
+    #[test]
+    fn strength_cache_rescores_changed_passwords() {
+        let mut entry = Entry {
+            id: crate::models::new_uuid(),
+            name: "GitHub".into(),
+            email: "me@example.com".into(),
+            password: "short".into(),
+            username: None,
+            notes: None,
+            totp_secret: None,
+            updated_at: None,
+            fields: Vec::new(),
+            attachments: Vec::new(),
+            entry_type: EntryType::Login,
+            ssh_key: None,
+            card: None,
+            sensitive: false,
+        };
+        let cache = StrengthCache::default();
+        assert_eq!(cache.get(&entry).label, "Weak");
+        assert_eq!(cache.get(&entry).label, "Weak");
+        entry.password = "Tr0ub4dor&3-horse-battery".into();
+        assert_eq!(cache.get(&entry).label, "Excellent");
+        assert_eq!(cache.scores.borrow().len(), 1);
+    }
+
+    #[test]
+    fn search_highlights_every_match_ignoring_case() {
+        assert_eq!(match_ranges("GitHub (github-ci)", "git"), [(0, 3), (8, 11)]);
+        assert!(match_ranges("GitHub", "").is_empty());
+        let spans = highlighted_spans("Café Git", Some("GIT"));
+        let texts: Vec<&str> = spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(texts, ["Café ", "Git"]);
+        assert_eq!(spans[1].style, search_style());
+        assert_eq!(highlighted_spans("", Some("x")).len(), 1);
+    }
+
+    #[test]
+    fn note_metadata_counts_chars_and_lines() {
+        let note = |content: &str| Note { id: "n".into(), title: "codes".into(), content: content.into() };
+        assert_eq!(note_metadata(&note("1111\n2222\n3333\n")), "15 chars · 3 lines");
+        assert_eq!(note_metadata(&note("é")), "1 char · 1 line");
+        assert_eq!(note_metadata(&note("")), "0 chars · 0 lines");
+        assert_eq!(note_detail_text(&note("x")), "Title: codes\n1 char · 1 line\n\nx");
+    }
+
+    #[test]
+    fn watched_secret_is_kept_until_due() {
+        let now = Instant::now();
+        let deadline = now + Duration::from_secs(20);
+        assert_eq!(watch_step("s3cret", Ok("s3cret".into()), now, deadline), WatchStep::Keep);
+        assert_eq!(
+            watch_step("s3cret", Err(arboard::Error::ClipboardOccupied), now, deadline),
+            WatchStep::Keep
+        );
+    }
+
+    #[test]
+    fn watched_secret_expires_at_deadline() {
+        let now = Instant::now();
+        assert_eq!(watch_step("s3cret", Ok("s3cret".into()), now, now), WatchStep::Expired);
+        // Due wins over a changed read, so a late poll still wipes.
+        assert_eq!(watch_step("s3cret", Ok("other".into()), now, now), WatchStep::Expired);
+    }
+
+    #[test]
+    fn replaced_content_is_left_alone() {
+        let now = Instant::now();
+        let deadline = now + Duration::from_secs(20);
+        assert_eq!(watch_step("s3cret", Ok("other".into()), now, deadline), WatchStep::Replaced);
+        assert_eq!(
+            watch_step("s3cret", Err(arboard::Error::ContentNotAvailable), now, deadline),
+            WatchStep::Replaced
+        );
+    }
+
+    #[test]
+    fn secret_outlived_by_an_osc52_copy_is_wiped_only_if_still_shown() {
+        assert!(still_holds("s3cret", Ok("s3cret".into())));
+        assert!(!still_holds("s3cret", Ok("other".into())));
+        assert!(!still_holds("s3cret", Err(arboard::Error::ContentNotAvailable)));
+    }
+
+    #[test]
+    fn entry_label_fills_placeholders_and_rejects_unknown_ones() {
+        let mut entry = Entry {
+            id: crate::models::new_uuid(),
+            name: "GitHub".into(),
+            email: "me@example.com".into(),
+            password: "x".into(),
+            username: None,
+            notes: None,
+            totp_secret: None,
+            updated_at: None,
+            fields: vec![CustomField {
+                label: "Login URL".into(),
+                value: "https://github.com/login".into(),
+                secret: false,
+            }],
+            attachments: Vec::new(),
+            entry_type: EntryType::Login,
+            ssh_key: None,
+            card: None,
+            sensitive: false,
+        };
+        assert_eq!(EntryLabel::parse("{user} ({email})").unwrap(), EntryLabel::default());
+        assert_eq!(EntryLabel::default().render(&entry), "me@example.com (me@example.com)");
+        let label = EntryLabel::parse("{{{username}}} {url}").unwrap();
+        assert_eq!(label.render(&entry), "{-} https://github.com/login");
+        entry.username = Some("octocat".into());
+        assert_eq!(label.render(&entry), "{octocat} https://github.com/login");
+        assert!(EntryLabel::parse("{login}").is_err());
+        assert!(EntryLabel::parse("{email").is_err());
+        assert!(EntryLabel::parse("email}").is_err());
+    }
 }
@@ -0,0 +1,106 @@
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use challenge_response::config::{Config, Mode, Slot};
+use challenge_response::hmacmode::Hmac as TokenHmac;
+use challenge_response::ChallengeResponse;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// Random bytes sent to the token; stored in the vault file, not secret.
+pub const CHALLENGE_LEN: usize = 32;
+/// The token pads challenges to this size; longer ones can't be sent.
+const MAX_CHALLENGE_LEN: usize = 64;
+/// Slot 2 is the one `ykman otp chalresp` and most guides program.
+const SLOT: Slot = Slot::Slot2;
+
+/// Last challenge and its response, so saves later in the session don't need
+/// another touch. Cleared by `forget_response`.
+struct CachedResponse {
+    challenge: Vec<u8>,
+    response: Zeroizing<Vec<u8>>,
+}
+
+static LAST_RESPONSE: Mutex<Option<CachedResponse>> = Mutex::new(None);
+
+/// HMAC-SHA1 response of the inserted token to `challenge`, read over USB.
+pub fn challenge_response(challenge: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let mut last = LAST_RESPONSE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(cached) = last.as_ref()
+        && cached.challenge == challenge
+    {
+        return Ok(cached.response.clone());
+    }
+    let challenge = encode_challenge(challenge)?;
+    let mut token = ChallengeResponse::new().map_err(|e| anyhow!("Cannot open USB devices: {e}"))?;
+    let device = token.find_device().map_err(|_| anyhow!("No YubiKey found; insert it and try again"))?;
+    let config = Config::new_from(device)
+        .set_variable_size(true)
+        .set_mode(Mode::Sha1)
+        .set_slot(SLOT);
+    let hmac = token
+        .challenge_response_hmac(challenge, config)
+        .map_err(|e| anyhow!("YubiKey did not respond: {e}"))?;
+    let response = parse_response(&hmac);
+    *last = Some(CachedResponse {
+        challenge: challenge.to_vec(),
+        response: response.clone(),
+    });
+    Ok(response)
+}
+
+/// Checks `challenge` fits one token frame; the device library would panic
+/// on a longer one.
+fn encode_challenge(challenge: &[u8]) -> Result<&[u8]> {
+    if challenge.is_empty() || challenge.len() > MAX_CHALLENGE_LEN {
+        return Err(anyhow!(
+            "YubiKey challenge must be 1-{MAX_CHALLENGE_LEN} bytes, got {}",
+            challenge.len()
+        ));
+    }
+    Ok(challenge)
+}
+
+/// The 20-byte HMAC-SHA1 from the token, copied out of the library's buffer
+/// (which wipes itself on drop).
+fn parse_response(hmac: &TokenHmac) -> Zeroizing<Vec<u8>> {
+    Zeroizing::new(hmac.to_vec())
+}
+
+pub fn forget_response() {
+    *LAST_RESPONSE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Final KEK: HMAC-SHA256 keyed by the Argon2 output over the token response,
+/// so neither the passphrase nor the token alone yields it.
+pub fn mix(kek: &[u8; 32], response: &[u8]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(kek).expect("HMAC accepts any key length");
+    mac.update(response);
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenge_must_fit_one_frame() {
+        assert_eq!(encode_challenge(&[7; CHALLENGE_LEN]).unwrap(), &[7; CHALLENGE_LEN]);
+        assert!(encode_challenge(&[0; MAX_CHALLENGE_LEN]).is_ok());
+        assert!(encode_challenge(&[]).is_err());
+        assert!(encode_challenge(&[0; MAX_CHALLENGE_LEN + 1]).is_err());
+    }
+
+    #[test]
+    fn response_is_the_full_hmac() {
+        let mut raw = [0u8; 20];
+        raw.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        let response = parse_response(&TokenHmac(raw));
+        assert_eq!(response.as_slice(), &raw);
+        // Either input changes the mixed KEK.
+        let kek = [1u8; 32];
+        assert_ne!(mix(&kek, &response), mix(&kek, &[0u8; 20]));
+        assert_ne!(mix(&kek, &response), mix(&[2u8; 32], &response));
+    }
+}